@@ -0,0 +1,11 @@
+#version 330 core
+
+in vec3 direction;
+
+out vec4 out_color;
+
+uniform samplerCube sky;
+
+void main() {
+    out_color = texture(sky, direction);
+}
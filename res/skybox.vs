@@ -0,0 +1,14 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+
+uniform mat4 view_projection;
+
+out vec3 direction;
+
+void main() {
+    direction = position;
+
+    // Force the depth to the far plane so the sky stays behind everything
+    gl_Position = (view_projection * vec4(position, 1.0)).xyww;
+}
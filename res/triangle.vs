@@ -4,8 +4,9 @@ layout (location = 0) in vec3 position;
 layout (location = 1) in vec4 color;
 layout (location = 2) in vec2 tex_coord;
 layout (location = 3) in vec3 normal;
+layout (location = 4) in mat4 model;
 
-uniform mat4 mvp;
+uniform mat4 view_projection;
 
 varying vec4 color0;
 varying vec2 tex_coord0;
@@ -14,5 +15,5 @@ void main() {
     color0 = color;
     tex_coord0 = tex_coord;
 
-    gl_Position = mvp * vec4(position, 1.0);
+    gl_Position = view_projection * model * vec4(position, 1.0);
 }
@@ -24,6 +24,11 @@ impl Camera {
         Matrix4::from(self.get_rotation().invert()) * Matrix4::from_translation(-self.position)
     }
 
+    /// The view matrix without the translation, for geometry that stays centered on the camera.
+    pub fn get_view_rotation_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from(self.get_rotation().invert())
+    }
+
     fn get_rotation(&self) -> Quaternion<f32> {
         Quaternion::from_angle_y(Deg(self.rotation.1))
             * Quaternion::from_angle_x(Deg(self.rotation.0))
@@ -1,4 +1,9 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
 use std::mem;
+use std::ops::{BitOr, Deref, DerefMut};
+use std::ptr;
+use std::slice;
 
 use gl;
 use gl::types::*;
@@ -6,21 +11,82 @@ use gl::types::*;
 #[derive(Debug)]
 pub struct Buffer {
     ty: BufferType,
+    usage: BufferUsage,
     id: GLuint,
+    size: Cell<usize>,
 }
 
 impl Buffer {
     pub fn new(ty: BufferType) -> Self {
+        Self::with_usage(ty, BufferUsage::Static)
+    }
+
+    pub fn with_usage(ty: BufferType, usage: BufferUsage) -> Self {
         unsafe {
             let mut id = 0;
             gl::GenBuffers(1, &mut id);
-            Buffer { ty, id }
+            Buffer {
+                ty,
+                usage,
+                id,
+                size: Cell::new(0),
+            }
         }
     }
 
-    pub fn bind(&self) -> ActiveBuffer {
+    pub fn ty(&self) -> BufferType {
+        self.ty
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// The allocated size in bytes.
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    pub fn bind(&self) -> ActiveBuffer<'_> {
         ActiveBuffer::new(self)
     }
+
+    /// Binds the buffer to an indexed binding point, such as a uniform block binding.
+    pub fn bind_base(&self, index: GLuint) {
+        assert!(
+            self.ty.is_indexed(),
+            "{:?} buffers have no indexed binding points",
+            self.ty
+        );
+        unsafe { gl::BindBufferBase(self.ty.into(), index, self.id) }
+    }
+
+    /// Binds `size` bytes starting at `offset` to an indexed binding point.
+    ///
+    /// `offset` must respect the target's alignment, e.g. `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    pub fn bind_range(&self, index: GLuint, offset: usize, size: usize) {
+        assert!(
+            self.ty.is_indexed(),
+            "{:?} buffers have no indexed binding points",
+            self.ty
+        );
+        assert!(
+            in_bounds(offset, size, self.size()),
+            "bind range of {} bytes at {} out of bounds for buffer of {} bytes",
+            size,
+            offset,
+            self.size()
+        );
+        unsafe {
+            gl::BindBufferRange(
+                self.ty.into(),
+                index,
+                self.id,
+                offset as GLintptr,
+                size as GLsizeiptr,
+            )
+        }
+    }
 }
 
 impl Drop for Buffer {
@@ -41,14 +107,130 @@ impl<'a> ActiveBuffer<'a> {
         ActiveBuffer { buffer }
     }
 
+    /// Replaces the buffer's storage with `data`.
     pub fn buffer(&self, data: &[u8]) {
         unsafe {
             gl::BufferData(
                 self.buffer.ty.into(),
                 mem::size_of_val(data) as GLsizeiptr,
                 data.as_ptr() as *const _,
-                gl::STATIC_DRAW,
+                self.buffer.usage.into(),
+            )
+        }
+        self.buffer.size.set(data.len());
+    }
+
+    /// Replaces the buffer's storage with `size` uninitialized bytes.
+    pub fn allocate(&self, size: usize) {
+        unsafe {
+            gl::BufferData(
+                self.buffer.ty.into(),
+                size as GLsizeiptr,
+                ptr::null(),
+                self.buffer.usage.into(),
+            )
+        }
+        self.buffer.size.set(size);
+    }
+
+    /// Allocates `size` bytes of immutable storage that can be mapped with `flags`.
+    ///
+    /// Requires `glBufferStorage` (GL 4.4). The storage can still be updated with `sub_data`.
+    pub fn storage(&self, size: usize, flags: MapAccess) {
+        unsafe {
+            gl::BufferStorage(
+                self.buffer.ty.into(),
+                size as GLsizeiptr,
+                ptr::null(),
+                flags.0 | gl::DYNAMIC_STORAGE_BIT,
+            )
+        }
+        self.buffer.size.set(size);
+    }
+
+    /// Overwrites part of the existing storage without reallocating it.
+    pub fn sub_data(&self, offset: usize, data: &[u8]) {
+        assert!(
+            in_bounds(offset, data.len(), self.buffer.size()),
+            "sub_data of {} bytes at {} out of bounds for buffer of {} bytes",
+            data.len(),
+            offset,
+            self.buffer.size()
+        );
+        unsafe {
+            gl::BufferSubData(
+                self.buffer.ty.into(),
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const _,
+            )
+        }
+    }
+
+    /// Copies the whole buffer back from the GPU.
+    pub fn read_back(&self) -> Vec<u8> {
+        let mut data = vec![0u8; self.buffer.size()];
+        unsafe {
+            gl::GetBufferSubData(
+                self.buffer.ty.into(),
+                0,
+                data.len() as GLsizeiptr,
+                data.as_mut_ptr() as *mut _,
+            )
+        }
+        data
+    }
+
+    /// Maps the whole buffer for reading.
+    pub fn map_read(&mut self) -> MappedBuffer<'_> {
+        self.map_range(0, self.buffer.size(), MapAccess::READ)
+    }
+
+    /// Maps the whole buffer for writing. Bytes that are not written keep their contents.
+    pub fn map_write(&mut self) -> MappedBuffer<'_> {
+        self.map_range(0, self.buffer.size(), MapAccess::WRITE)
+    }
+
+    /// Maps `len` bytes starting at `offset`. The buffer is unmapped when the guard drops.
+    /// The guard borrows the `ActiveBuffer` mutably, so the buffer can't be mapped twice or
+    /// written with `sub_data` while it is mapped.
+    ///
+    /// GL can't map an empty range, so a `len` of 0 returns an empty guard that maps nothing.
+    pub fn map_range(&mut self, offset: usize, len: usize, access: MapAccess) -> MappedBuffer<'_> {
+        assert!(
+            in_bounds(offset, len, self.buffer.size()),
+            "map range of {} bytes at {} out of bounds for buffer of {} bytes",
+            len,
+            offset,
+            self.buffer.size()
+        );
+        let target = self.buffer.ty.into();
+        if len == 0 {
+            return MappedBuffer {
+                target,
+                ptr: ptr::NonNull::dangling().as_ptr(),
+                len,
+                access,
+                _active: PhantomData,
+            };
+        }
+
+        let ptr = unsafe {
+            gl::MapBufferRange(
+                self.buffer.ty.into(),
+                offset as GLintptr,
+                len as GLsizeiptr,
+                access.0,
             )
+        };
+        assert!(!ptr.is_null(), "failed to map buffer {}", self.buffer.id);
+
+        MappedBuffer {
+            target,
+            ptr: ptr as *mut u8,
+            len,
+            access,
+            _active: PhantomData,
         }
     }
 }
@@ -59,10 +241,128 @@ impl<'a> Drop for ActiveBuffer<'a> {
     }
 }
 
+/// A mapped range of an `ActiveBuffer`.
+pub struct MappedBuffer<'a> {
+    target: GLenum,
+    ptr: *mut u8,
+    len: usize,
+    access: MapAccess,
+    _active: PhantomData<&'a ActiveBuffer<'a>>,
+}
+
+impl<'a> MappedBuffer<'a> {
+    /// The length of the mapped range in bytes. Unlike dereferencing, this works for write-only
+    /// mappings.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Makes writes to part of a range mapped with `MapAccess::FLUSH_EXPLICIT` visible.
+    pub fn flush(&self, offset: usize, len: usize) {
+        assert!(in_bounds(offset, len, self.len));
+        if self.len == 0 {
+            return;
+        }
+        unsafe { gl::FlushMappedBufferRange(self.target, offset as GLintptr, len as GLsizeiptr) }
+    }
+}
+
+impl<'a> Deref for MappedBuffer<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        assert!(
+            self.access.contains(MapAccess::READ),
+            "buffer was not mapped for reading"
+        );
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a> DerefMut for MappedBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        assert!(
+            self.access.contains(MapAccess::WRITE),
+            "buffer was not mapped for writing"
+        );
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'a> Drop for MappedBuffer<'a> {
+    fn drop(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        unsafe {
+            gl::UnmapBuffer(self.target);
+        }
+    }
+}
+
+/// Whether `len` bytes at `offset` fit in `size` bytes, without overflowing.
+fn in_bounds(offset: usize, len: usize, size: usize) -> bool {
+    offset.checked_add(len).is_some_and(|end| end <= size)
+}
+
+/// Flags for `ActiveBuffer::map_range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapAccess(GLbitfield);
+
+impl MapAccess {
+    pub const READ: MapAccess = MapAccess(gl::MAP_READ_BIT);
+    pub const WRITE: MapAccess = MapAccess(gl::MAP_WRITE_BIT);
+    pub const INVALIDATE_RANGE: MapAccess = MapAccess(gl::MAP_INVALIDATE_RANGE_BIT);
+    pub const INVALIDATE_BUFFER: MapAccess = MapAccess(gl::MAP_INVALIDATE_BUFFER_BIT);
+    pub const FLUSH_EXPLICIT: MapAccess = MapAccess(gl::MAP_FLUSH_EXPLICIT_BIT);
+    pub const UNSYNCHRONIZED: MapAccess = MapAccess(gl::MAP_UNSYNCHRONIZED_BIT);
+    pub const PERSISTENT: MapAccess = MapAccess(gl::MAP_PERSISTENT_BIT);
+    pub const COHERENT: MapAccess = MapAccess(gl::MAP_COHERENT_BIT);
+
+    pub fn contains(self, other: MapAccess) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MapAccess {
+    type Output = MapAccess;
+
+    fn bitor(self, other: MapAccess) -> MapAccess {
+        MapAccess(self.0 | other.0)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BufferType {
     Vertex,
     Index,
+    Uniform,
+    ShaderStorage,
+    AtomicCounter,
+    /// Arguments for `glDraw*Indirect` and `glDispatchComputeIndirect`.
+    DrawIndirect,
+    /// Destination of `glReadPixels` and `glGetTexImage`.
+    PixelPack,
+    /// Source of `glTexImage*` and `glTexSubImage*`.
+    PixelUnpack,
+    TransformFeedback,
+}
+
+impl BufferType {
+    /// Whether the target has indexed binding points for `bind_base` and `bind_range`.
+    pub fn is_indexed(self) -> bool {
+        matches!(
+            self,
+            BufferType::Uniform
+                | BufferType::ShaderStorage
+                | BufferType::AtomicCounter
+                | BufferType::TransformFeedback
+        )
+    }
 }
 
 impl From<BufferType> for GLenum {
@@ -70,6 +370,34 @@ impl From<BufferType> for GLenum {
         match buffer {
             BufferType::Vertex => gl::ARRAY_BUFFER,
             BufferType::Index => gl::ELEMENT_ARRAY_BUFFER,
+            BufferType::Uniform => gl::UNIFORM_BUFFER,
+            BufferType::ShaderStorage => gl::SHADER_STORAGE_BUFFER,
+            BufferType::AtomicCounter => gl::ATOMIC_COUNTER_BUFFER,
+            BufferType::DrawIndirect => gl::DRAW_INDIRECT_BUFFER,
+            BufferType::PixelPack => gl::PIXEL_PACK_BUFFER,
+            BufferType::PixelUnpack => gl::PIXEL_UNPACK_BUFFER,
+            BufferType::TransformFeedback => gl::TRANSFORM_FEEDBACK_BUFFER,
+        }
+    }
+}
+
+/// How often the contents of a buffer are expected to change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferUsage {
+    /// Written once and drawn many times.
+    Static,
+    /// Rewritten occasionally and drawn many times.
+    Dynamic,
+    /// Rewritten for nearly every draw.
+    Stream,
+}
+
+impl From<BufferUsage> for GLenum {
+    fn from(usage: BufferUsage) -> Self {
+        match usage {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
         }
     }
 }
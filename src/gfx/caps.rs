@@ -0,0 +1,32 @@
+use std::ffi::CStr;
+
+use gl;
+use gl::types::*;
+
+/// The context's `(major, minor)` GL version.
+pub fn version() -> (GLint, GLint) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor)
+}
+
+/// Whether the context reports any of the extensions in `names`.
+pub fn has_extension(names: &[&str]) -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as GLuint).any(|i| {
+            let name = CStr::from_ptr(gl::GetStringi(gl::EXTENSIONS, i) as *const _);
+            names.iter().any(|n| name.to_bytes() == n.as_bytes())
+        })
+    }
+}
+
+/// Whether `glBufferStorage` can be used. A loaded function pointer is not enough, as GLX
+/// hands out pointers for functions the driver doesn't implement.
+pub fn buffer_storage() -> bool {
+    version() >= (4, 4) || has_extension(&["GL_ARB_buffer_storage"])
+}
@@ -0,0 +1,230 @@
+/// A color channel value that can be filtered.
+pub trait Sample: Copy {
+    const MAX: u32;
+
+    fn to_u32(self) -> u32;
+    fn from_u32(value: u32) -> Self;
+}
+
+impl Sample for u8 {
+    const MAX: u32 = u8::MAX as u32;
+
+    fn to_u32(self) -> u32 {
+        u32::from(self)
+    }
+
+    fn from_u32(value: u32) -> Self {
+        value as u8
+    }
+}
+
+impl Sample for u16 {
+    const MAX: u32 = u16::MAX as u32;
+
+    fn to_u32(self) -> u32 {
+        u32::from(self)
+    }
+
+    fn from_u32(value: u32) -> Self {
+        value as u16
+    }
+}
+
+/// The number of levels in a full mip chain, down to and including 1x1.
+pub fn level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// The size of the next smaller mip level.
+pub fn next_size(width: u32, height: u32) -> (u32, u32) {
+    ((width / 2).max(1), (height / 2).max(1))
+}
+
+/// Halves an image with a box filter. Even sizes average each pair of texels. Odd sizes use
+/// three overlapping texels per output with weights that sum to the full input, so no row or
+/// column is dropped.
+pub fn downsample_box<T: Sample>(data: &[T], width: u32, height: u32, channels: usize) -> Vec<T> {
+    let (w, h) = (width as usize, height as usize);
+    let (new_w, new_h) = next_size(width, height);
+    let columns: Vec<_> = (0..new_w as usize).map(|x| box_taps(w, x)).collect();
+    let rows: Vec<_> = (0..new_h as usize).map(|y| box_taps(h, y)).collect();
+
+    let mut out = Vec::with_capacity(columns.len() * rows.len() * channels);
+    for &(ref row_taps, row_total) in &rows {
+        for &(ref column_taps, column_total) in &columns {
+            let total = row_total * column_total;
+            for c in 0..channels {
+                let mut sum = 0;
+                for &(row, row_weight) in row_taps {
+                    for &(column, column_weight) in column_taps {
+                        let value = data[(row * w + column) * channels + c].to_u32();
+                        sum += u64::from(value) * row_weight * column_weight;
+                    }
+                }
+                out.push(T::from_u32(((sum + total / 2) / total) as u32));
+            }
+        }
+    }
+    out
+}
+
+/// The input texels and weights making up output texel `i` when halving `n` texels, along
+/// with the sum of the weights.
+fn box_taps(n: usize, i: usize) -> (Vec<(usize, u64)>, u64) {
+    if n == 1 {
+        (vec![(0, 1)], 1)
+    } else if n.is_multiple_of(2) {
+        (vec![(2 * i, 1), (2 * i + 1, 1)], 2)
+    } else {
+        let out = (n as u64 - 1) / 2;
+        let i64 = i as u64;
+        (
+            vec![(2 * i, out - i64), (2 * i + 1, out), (2 * i + 2, i64 + 1)],
+            n as u64,
+        )
+    }
+}
+
+/// Halves an image with a separable Lanczos filter of 3 lobes, which keeps smaller levels
+/// sharper than a box filter at the cost of slight ringing around hard edges.
+pub fn downsample_lanczos<T: Sample>(
+    data: &[T],
+    width: u32,
+    height: u32,
+    channels: usize,
+) -> Vec<T> {
+    let (w, h) = (width as usize, height as usize);
+    let (new_w, new_h) = next_size(width, height);
+    let (new_w, new_h) = (new_w as usize, new_h as usize);
+
+    // Filter the rows first, then the columns of the result
+    let columns: Vec<_> = (0..new_w).map(|x| lanczos_taps(w, new_w, x)).collect();
+    let mut horizontal = Vec::with_capacity(new_w * h * channels);
+    for y in 0..h {
+        for taps in &columns {
+            for c in 0..channels {
+                let sum: f32 = taps
+                    .iter()
+                    .map(|&(x, weight)| data[(y * w + x) * channels + c].to_u32() as f32 * weight)
+                    .sum();
+                horizontal.push(sum);
+            }
+        }
+    }
+
+    let rows: Vec<_> = (0..new_h).map(|y| lanczos_taps(h, new_h, y)).collect();
+    let mut out = Vec::with_capacity(new_w * new_h * channels);
+    for taps in &rows {
+        for x in 0..new_w {
+            for c in 0..channels {
+                let sum: f32 = taps
+                    .iter()
+                    .map(|&(y, weight)| horizontal[(y * new_w + x) * channels + c] * weight)
+                    .sum();
+                out.push(T::from_u32(sum.round().clamp(0.0, T::MAX as f32) as u32));
+            }
+        }
+    }
+    out
+}
+
+const LANCZOS_LOBES: f32 = 3.0;
+
+/// The normalized input weights for output texel `i` when resampling `n` texels to `new_n`.
+/// Taps past either edge repeat the edge texel.
+fn lanczos_taps(n: usize, new_n: usize, i: usize) -> Vec<(usize, f32)> {
+    let scale = n as f32 / new_n as f32;
+    let center = (i as f32 + 0.5) * scale - 0.5;
+    let radius = LANCZOS_LOBES * scale;
+
+    let first = (center - radius).ceil() as isize;
+    let last = (center + radius).floor() as isize;
+    let mut taps: Vec<(usize, f32)> = (first..=last)
+        .map(|j| {
+            let texel = j.clamp(0, n as isize - 1) as usize;
+            (texel, lanczos((j as f32 - center) / scale))
+        })
+        .filter(|&(_, weight)| weight != 0.0)
+        .collect();
+    let total: f32 = taps.iter().map(|&(_, weight)| weight).sum();
+    for tap in &mut taps {
+        tap.1 /= total;
+    }
+    taps
+}
+
+fn lanczos(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= LANCZOS_LOBES {
+        return 0.0;
+    }
+    let px = std::f32::consts::PI * x;
+    LANCZOS_LOBES * px.sin() * (px / LANCZOS_LOBES).sin() / (px * px)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_count_includes_1x1() {
+        assert_eq!(level_count(1, 1), 1);
+        assert_eq!(level_count(2, 1), 2);
+        assert_eq!(level_count(256, 256), 9);
+        assert_eq!(level_count(300, 5), 9);
+        assert_eq!(level_count(0, 0), 1);
+    }
+
+    #[test]
+    fn next_size_stops_at_1() {
+        assert_eq!(next_size(8, 3), (4, 1));
+        assert_eq!(next_size(1, 1), (1, 1));
+    }
+
+    #[test]
+    fn box_averages_even_blocks() {
+        let data: [u8; 8] = [0, 10, 20, 30, 40, 50, 60, 70];
+        assert_eq!(downsample_box(&data, 4, 2, 1), [25, 45]);
+    }
+
+    #[test]
+    fn box_keeps_every_texel_of_odd_sizes() {
+        // The last column must contribute rather than being dropped
+        let data: [u8; 3] = [0, 0, 255];
+        assert_eq!(downsample_box(&data, 3, 1, 1), [85]);
+
+        let data: [u8; 5] = [0, 10, 20, 30, 40];
+        assert_eq!(downsample_box(&data, 5, 1, 1), [8, 32]);
+    }
+
+    #[test]
+    fn box_filters_odd_rows_and_channels() {
+        #[rustfmt::skip]
+        let data: [u16; 6] = [
+            0, 300,
+            300, 600,
+            600, 900,
+        ];
+        assert_eq!(downsample_box(&data, 1, 3, 2), [300, 600]);
+    }
+
+    #[test]
+    fn lanczos_keeps_flat_images_flat() {
+        let data = vec![200u8; 7 * 5 * 3];
+        let out = downsample_lanczos(&data, 7, 5, 3);
+        assert_eq!(out.len(), 3 * 2 * 3);
+        assert!(out.iter().all(|&v| v == 200));
+    }
+
+    #[test]
+    fn lanczos_clamps_ringing() {
+        let data: [u8; 8] = [0, 0, 0, 0, 255, 255, 255, 255];
+        let out = downsample_lanczos(&data, 8, 1, 1);
+        assert_eq!(out.len(), 4);
+        assert_eq!(out[0], 0);
+        assert_eq!(out[3], 255);
+        assert!(out[1] < out[2]);
+    }
+}
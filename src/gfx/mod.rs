@@ -1,4 +1,17 @@
 pub mod buffer;
+pub mod caps;
+pub mod mipmap;
+pub mod preprocessor;
+pub mod program_cache;
+pub mod reflection;
+pub mod reload;
+pub mod ring_buffer;
 pub mod shader;
+pub mod std140;
+pub mod sync;
 pub mod texture;
+pub mod typed_buffer;
+pub mod uniform;
+pub mod variants;
 pub mod vertex_array;
+pub mod vertex_format;
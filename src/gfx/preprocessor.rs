@@ -0,0 +1,275 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gfx::shader::{ShaderError, ShaderSource, ShaderStage};
+
+/// Expands `#include` directives and injects `#define`s into GLSL sources.
+///
+/// Includes are resolved against `root`, normally the resource directory.
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    root: PathBuf,
+    defines: Vec<(String, String)>,
+}
+
+impl Preprocessor {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Preprocessor {
+            root: root.into(),
+            defines: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn define<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    /// Preprocesses the file `name`, relative to the root.
+    pub fn process_file(&self, name: &str) -> Result<PreprocessedSource, ShaderError> {
+        let src = self.read(name)?;
+        self.process(name, &src)
+    }
+
+    /// Preprocesses `src`, reporting its lines as coming from `name`.
+    pub fn process(&self, name: &str, src: &str) -> Result<PreprocessedSource, ShaderError> {
+        let mut out = PreprocessedSource {
+            source: String::new(),
+            line_map: LineMap::default(),
+        };
+
+        // Defines have to follow #version, which must be the first directive
+        let has_version = src.lines().any(|l| l.trim_start().starts_with("#version"));
+        if !has_version {
+            self.push_defines(&mut out);
+        }
+
+        let mut stack = Vec::new();
+        self.expand(name, src, &mut out, &mut stack, has_version)?;
+        Ok(out)
+    }
+
+    fn expand(
+        &self,
+        name: &str,
+        src: &str,
+        out: &mut PreprocessedSource,
+        stack: &mut Vec<String>,
+        mut defines_pending: bool,
+    ) -> Result<(), ShaderError> {
+        let file = out.line_map.add_file(name);
+        stack.push(name.to_owned());
+
+        for (i, line) in src.lines().enumerate() {
+            let number = i as u32 + 1;
+            let directive = line.trim_start();
+            if directive.starts_with("#include") {
+                let error = |message: &str| ShaderError::Preprocess {
+                    file: name.to_owned(),
+                    line: number,
+                    message: message.to_owned(),
+                };
+                let path = parse_include(directive).ok_or_else(|| error("malformed #include"))?;
+                if stack.iter().any(|f| f == path) {
+                    return Err(error(&format!("recursive #include of \"{}\"", path)));
+                }
+                let included = fs::read_to_string(self.root.join(path))
+                    .map_err(|e| error(&format!("can't read \"{}\": {}", path, e)))?;
+                self.expand(path, &included, out, stack, false)?;
+            } else {
+                out.push_line(line, Some((file, number)));
+                if defines_pending && directive.starts_with("#version") {
+                    self.push_defines(out);
+                    defines_pending = false;
+                }
+            }
+        }
+
+        stack.pop();
+        Ok(())
+    }
+
+    fn push_defines(&self, out: &mut PreprocessedSource) {
+        for (name, value) in &self.defines {
+            out.push_line(&format!("#define {} {}", name, value), None);
+        }
+    }
+
+    fn read(&self, name: &str) -> Result<String, ShaderError> {
+        let path = self.root.join(name);
+        fs::read_to_string(&path).map_err(|error| ShaderError::Io { path, error })
+    }
+}
+
+fn parse_include(directive: &str) -> Option<&str> {
+    let rest = directive["#include".len()..].trim();
+    let (open, close) = match rest.chars().next() {
+        Some('"') => ('"', '"'),
+        Some('<') => ('<', '>'),
+        _ => return None,
+    };
+    let rest = &rest[open.len_utf8()..];
+    rest.find(close).map(|end| &rest[..end])
+}
+
+/// The output of the `Preprocessor`.
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    pub source: String,
+    pub line_map: LineMap,
+}
+
+impl PreprocessedSource {
+    pub fn shader_source(&self, stage: ShaderStage) -> ShaderSource<'_> {
+        ShaderSource::new(stage, &self.source).with_line_map(&self.line_map)
+    }
+
+    fn push_line(&mut self, line: &str, origin: Option<(usize, u32)>) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.line_map.lines.push(origin);
+    }
+}
+
+/// Maps lines of a preprocessed source back to the file and line they came from.
+#[derive(Debug, Clone, Default)]
+pub struct LineMap {
+    files: Vec<String>,
+    lines: Vec<Option<(usize, u32)>>,
+}
+
+impl LineMap {
+    /// Resolves a 1-based line of the preprocessed source. Injected lines resolve to `None`.
+    pub fn resolve(&self, line: u32) -> Option<(&str, u32)> {
+        let index = (line as usize).checked_sub(1)?;
+        self.lines
+            .get(index)
+            .and_then(|l| *l)
+            .map(|(file, line)| (&self.files[file][..], line))
+    }
+
+    /// Every file that contributed to the source.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    fn add_file(&mut self, name: &str) -> usize {
+        match self.files.iter().position(|f| f == name) {
+            Some(i) => i,
+            None => {
+                self.files.push(name.to_owned());
+                self.files.len() - 1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    /// Writes `files` to a fresh directory for a preprocessor to include from.
+    fn root(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("gl-sandbox-{}-{}", process::id(), test));
+        fs::create_dir_all(&dir).unwrap();
+        for &(name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    fn preprocess_error(error: ShaderError) -> (String, u32, String) {
+        match error {
+            ShaderError::Preprocess {
+                file,
+                line,
+                message,
+            } => (file, line, message),
+            error => panic!("expected a preprocess error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn defines_follow_version() {
+        let mut preprocessor = Preprocessor::new(root("defines", &[]));
+        preprocessor.define("SHADOWS", "1");
+
+        let out = preprocessor
+            .process("main.vs", "// header\n#version 330\nvoid main() {}\n")
+            .unwrap();
+        assert_eq!(
+            out.source,
+            "// header\n#version 330\n#define SHADOWS 1\nvoid main() {}\n"
+        );
+
+        let out = preprocessor.process("main.vs", "void main() {}\n").unwrap();
+        assert_eq!(out.source, "#define SHADOWS 1\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn line_map_resolves_included_lines() {
+        let root = root("line-map", &[("common.glsl", "float a;\nfloat b;\n")]);
+        let mut preprocessor = Preprocessor::new(root);
+        preprocessor.define("X", "1");
+
+        let src = "#version 330\n#include \"common.glsl\"\nvoid main() {}\n";
+        let out = preprocessor.process("main.vs", src).unwrap();
+        let map = &out.line_map;
+        assert_eq!(map.resolve(1), Some(("main.vs", 1)));
+        assert_eq!(map.resolve(2), None);
+        assert_eq!(map.resolve(3), Some(("common.glsl", 1)));
+        assert_eq!(map.resolve(4), Some(("common.glsl", 2)));
+        assert_eq!(map.resolve(5), Some(("main.vs", 3)));
+        assert_eq!(map.resolve(6), None);
+        assert_eq!(map.resolve(0), None);
+        assert_eq!(map.files(), ["main.vs", "common.glsl"]);
+    }
+
+    #[test]
+    fn parses_include_paths() {
+        assert_eq!(parse_include("#include \"a/b.glsl\""), Some("a/b.glsl"));
+        assert_eq!(
+            parse_include("#include   <lights.glsl>"),
+            Some("lights.glsl")
+        );
+        assert_eq!(parse_include("#include lights.glsl"), None);
+        assert_eq!(parse_include("#include \"unterminated"), None);
+    }
+
+    #[test]
+    fn rejects_recursive_includes() {
+        let root = root(
+            "recursive",
+            &[
+                ("a.glsl", "#include \"b.glsl\"\n"),
+                ("b.glsl", "float x;\n#include \"a.glsl\"\n"),
+            ],
+        );
+        let error = Preprocessor::new(root).process_file("a.glsl").unwrap_err();
+        let (file, line, message) = preprocess_error(error);
+        assert_eq!((&file[..], line), ("b.glsl", 2));
+        assert!(message.contains("recursive"), "{}", message);
+    }
+
+    #[test]
+    fn reports_where_a_missing_include_was_requested() {
+        let preprocessor = Preprocessor::new(root("missing", &[]));
+        let error = preprocessor
+            .process("main.fs", "#version 330\n#include \"missing.glsl\"\n")
+            .unwrap_err();
+        let (file, line, message) = preprocess_error(error);
+        assert_eq!((&file[..], line), ("main.fs", 2));
+        assert!(message.contains("missing.glsl"), "{}", message);
+    }
+}
@@ -0,0 +1,111 @@
+use std::ffi::CStr;
+use std::fs;
+use std::path::PathBuf;
+
+use gfx::shader::{Program, Shader, ShaderError, ShaderSource, ShaderStage};
+
+use gl;
+use gl::types::*;
+
+/// Stores linked program binaries on disk so later runs can skip compiling and linking.
+///
+/// Entries are keyed by the shader sources and the driver, so an updated driver or edited
+/// shader simply misses the cache.
+pub struct ProgramCache {
+    dir: PathBuf,
+}
+
+impl ProgramCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        ProgramCache { dir: dir.into() }
+    }
+
+    /// Loads the program from the cache, or builds it from `sources` and stores the result.
+    ///
+    /// Falls back to building from source whenever the driver does not support binaries, the
+    /// cache cannot be read or written, or the cached binary is rejected.
+    pub fn load_or_build(&self, sources: &[ShaderSource]) -> Result<Program, ShaderError> {
+        if !binaries_supported() {
+            return Program::from_sources(sources);
+        }
+
+        let stages: Vec<ShaderStage> = sources.iter().map(|s| s.stage()).collect();
+        let path = self.dir.join(format!("{:016x}.bin", cache_key(sources)));
+        if let Ok(data) = fs::read(&path) {
+            if data.len() > 4 {
+                let format = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+                if let Ok(program) = Program::from_binary(format, &data[4..], &stages) {
+                    return Ok(program);
+                }
+            }
+        }
+
+        let shaders = sources
+            .iter()
+            .map(|&s| Shader::compile_source(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let program = Program::from_shaders_retrievable(&shaders)?;
+        if let Some((format, binary)) = program.binary() {
+            let mut data = Vec::with_capacity(4 + binary.len());
+            data.extend_from_slice(&format.to_le_bytes());
+            data.extend_from_slice(&binary);
+            // The cache is only an optimisation, so failing to write it is not an error
+            let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, data));
+        }
+        Ok(program)
+    }
+}
+
+fn binaries_supported() -> bool {
+    let mut formats = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats) }
+    formats > 0
+}
+
+/// Hashes the driver strings and shader sources with FNV-1a, which unlike `DefaultHasher` gives
+/// the same key across Rust releases.
+fn cache_key(sources: &[ShaderSource]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    for &name in &[gl::VENDOR, gl::RENDERER, gl::VERSION] {
+        hasher.write_str(&gl_string(name));
+    }
+    for source in sources {
+        hasher.write(&GLenum::from(source.stage()).to_le_bytes());
+        hasher.write_str(source.src());
+    }
+    hasher.0
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Writes the length first so that consecutive strings can't run into each other.
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+}
+
+fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let ptr = gl::GetString(name);
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr as *const _)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+}
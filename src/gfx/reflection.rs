@@ -0,0 +1,385 @@
+use std::ffi::CString;
+use std::fmt;
+
+use gl;
+use gl::types::*;
+
+/// The type of a uniform or vertex attribute as reported by the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    BVec2,
+    BVec3,
+    BVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+    Mat2x3,
+    Mat2x4,
+    Mat3x2,
+    Mat3x4,
+    Mat4x2,
+    Mat4x3,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler2DArray,
+    Sampler2DShadow,
+    Sampler2DRect,
+    Other(GLenum),
+}
+
+impl GlslType {
+    /// The number of attribute locations and the components in each, e.g. `(4, 4)` for `mat4`.
+    pub fn attrib_shape(self) -> (GLint, GLint) {
+        match self {
+            GlslType::Float | GlslType::Int | GlslType::UInt | GlslType::Bool => (1, 1),
+            GlslType::Vec2 | GlslType::IVec2 | GlslType::UVec2 | GlslType::BVec2 => (1, 2),
+            GlslType::Vec3 | GlslType::IVec3 | GlslType::UVec3 | GlslType::BVec3 => (1, 3),
+            GlslType::Vec4 | GlslType::IVec4 | GlslType::UVec4 | GlslType::BVec4 => (1, 4),
+            GlslType::Mat2 => (2, 2),
+            GlslType::Mat3 => (3, 3),
+            GlslType::Mat4 => (4, 4),
+            GlslType::Mat2x3 => (2, 3),
+            GlslType::Mat2x4 => (2, 4),
+            GlslType::Mat3x2 => (3, 2),
+            GlslType::Mat3x4 => (3, 4),
+            GlslType::Mat4x2 => (4, 2),
+            GlslType::Mat4x3 => (4, 3),
+            _ => (1, 1),
+        }
+    }
+
+    /// Whether the type is an `int` or `uint` scalar or vector, which needs integer vertex data.
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            GlslType::Int
+                | GlslType::IVec2
+                | GlslType::IVec3
+                | GlslType::IVec4
+                | GlslType::UInt
+                | GlslType::UVec2
+                | GlslType::UVec3
+                | GlslType::UVec4
+        )
+    }
+
+    /// Whether the type is any kind of sampler, including those without their own variant.
+    pub fn is_sampler(self) -> bool {
+        match self {
+            GlslType::Sampler2D
+            | GlslType::Sampler3D
+            | GlslType::SamplerCube
+            | GlslType::Sampler2DArray
+            | GlslType::Sampler2DShadow
+            | GlslType::Sampler2DRect => true,
+            GlslType::Other(ty) => SAMPLER_TYPES.contains(&ty),
+            _ => false,
+        }
+    }
+
+    /// Whether the type is an image, which is set to the index of an image unit.
+    pub fn is_image(self) -> bool {
+        match self {
+            GlslType::Other(ty) => IMAGE_TYPES.contains(&ty),
+            _ => false,
+        }
+    }
+}
+
+// Every sampler type, as reported by glGetActiveUniform
+const SAMPLER_TYPES: &[GLenum] = &[
+    gl::SAMPLER_1D,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_1D_ARRAY,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_2D_MULTISAMPLE,
+    gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::SAMPLER_BUFFER,
+    gl::SAMPLER_2D_RECT,
+    gl::SAMPLER_CUBE_MAP_ARRAY,
+    gl::SAMPLER_1D_SHADOW,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_1D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_CUBE_SHADOW,
+    gl::SAMPLER_2D_RECT_SHADOW,
+    gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW,
+    gl::INT_SAMPLER_1D,
+    gl::INT_SAMPLER_2D,
+    gl::INT_SAMPLER_3D,
+    gl::INT_SAMPLER_CUBE,
+    gl::INT_SAMPLER_1D_ARRAY,
+    gl::INT_SAMPLER_2D_ARRAY,
+    gl::INT_SAMPLER_2D_MULTISAMPLE,
+    gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::INT_SAMPLER_BUFFER,
+    gl::INT_SAMPLER_2D_RECT,
+    gl::INT_SAMPLER_CUBE_MAP_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_1D,
+    gl::UNSIGNED_INT_SAMPLER_2D,
+    gl::UNSIGNED_INT_SAMPLER_3D,
+    gl::UNSIGNED_INT_SAMPLER_CUBE,
+    gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_BUFFER,
+    gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+    gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY,
+];
+
+// Every image type, as reported by glGetActiveUniform
+const IMAGE_TYPES: &[GLenum] = &[
+    gl::IMAGE_1D,
+    gl::IMAGE_2D,
+    gl::IMAGE_3D,
+    gl::IMAGE_CUBE,
+    gl::IMAGE_1D_ARRAY,
+    gl::IMAGE_2D_ARRAY,
+    gl::IMAGE_2D_MULTISAMPLE,
+    gl::IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::IMAGE_BUFFER,
+    gl::IMAGE_2D_RECT,
+    gl::IMAGE_CUBE_MAP_ARRAY,
+    gl::INT_IMAGE_1D,
+    gl::INT_IMAGE_2D,
+    gl::INT_IMAGE_3D,
+    gl::INT_IMAGE_CUBE,
+    gl::INT_IMAGE_1D_ARRAY,
+    gl::INT_IMAGE_2D_ARRAY,
+    gl::INT_IMAGE_2D_MULTISAMPLE,
+    gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::INT_IMAGE_BUFFER,
+    gl::INT_IMAGE_2D_RECT,
+    gl::INT_IMAGE_CUBE_MAP_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_1D,
+    gl::UNSIGNED_INT_IMAGE_2D,
+    gl::UNSIGNED_INT_IMAGE_3D,
+    gl::UNSIGNED_INT_IMAGE_CUBE,
+    gl::UNSIGNED_INT_IMAGE_1D_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_2D_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_BUFFER,
+    gl::UNSIGNED_INT_IMAGE_2D_RECT,
+    gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY,
+];
+
+impl From<GLenum> for GlslType {
+    fn from(ty: GLenum) -> Self {
+        match ty {
+            gl::FLOAT => GlslType::Float,
+            gl::FLOAT_VEC2 => GlslType::Vec2,
+            gl::FLOAT_VEC3 => GlslType::Vec3,
+            gl::FLOAT_VEC4 => GlslType::Vec4,
+            gl::INT => GlslType::Int,
+            gl::INT_VEC2 => GlslType::IVec2,
+            gl::INT_VEC3 => GlslType::IVec3,
+            gl::INT_VEC4 => GlslType::IVec4,
+            gl::UNSIGNED_INT => GlslType::UInt,
+            gl::UNSIGNED_INT_VEC2 => GlslType::UVec2,
+            gl::UNSIGNED_INT_VEC3 => GlslType::UVec3,
+            gl::UNSIGNED_INT_VEC4 => GlslType::UVec4,
+            gl::BOOL => GlslType::Bool,
+            gl::BOOL_VEC2 => GlslType::BVec2,
+            gl::BOOL_VEC3 => GlslType::BVec3,
+            gl::BOOL_VEC4 => GlslType::BVec4,
+            gl::FLOAT_MAT2 => GlslType::Mat2,
+            gl::FLOAT_MAT3 => GlslType::Mat3,
+            gl::FLOAT_MAT4 => GlslType::Mat4,
+            gl::FLOAT_MAT2x3 => GlslType::Mat2x3,
+            gl::FLOAT_MAT2x4 => GlslType::Mat2x4,
+            gl::FLOAT_MAT3x2 => GlslType::Mat3x2,
+            gl::FLOAT_MAT3x4 => GlslType::Mat3x4,
+            gl::FLOAT_MAT4x2 => GlslType::Mat4x2,
+            gl::FLOAT_MAT4x3 => GlslType::Mat4x3,
+            gl::SAMPLER_2D => GlslType::Sampler2D,
+            gl::SAMPLER_3D => GlslType::Sampler3D,
+            gl::SAMPLER_CUBE => GlslType::SamplerCube,
+            gl::SAMPLER_2D_ARRAY => GlslType::Sampler2DArray,
+            gl::SAMPLER_2D_SHADOW => GlslType::Sampler2DShadow,
+            gl::SAMPLER_2D_RECT => GlslType::Sampler2DRect,
+            ty => GlslType::Other(ty),
+        }
+    }
+}
+
+/// An active uniform or vertex attribute of a linked program.
+#[derive(Debug, Clone)]
+pub struct ShaderVariable {
+    /// The name, without the `[0]` suffix drivers report for arrays.
+    pub name: String,
+    pub ty: GlslType,
+    /// The number of array elements, 1 for non-arrays.
+    pub size: GLint,
+    /// The location, or -1 for variables that live in a uniform block.
+    pub location: GLint,
+}
+
+pub fn active_uniforms(program: GLuint) -> Vec<ShaderVariable> {
+    active_variables(
+        program,
+        gl::ACTIVE_UNIFORMS,
+        gl::ACTIVE_UNIFORM_MAX_LENGTH,
+        gl::GetActiveUniform,
+        gl::GetUniformLocation,
+    )
+}
+
+pub fn active_attributes(program: GLuint) -> Vec<ShaderVariable> {
+    active_variables(
+        program,
+        gl::ACTIVE_ATTRIBUTES,
+        gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+        gl::GetActiveAttrib,
+        gl::GetAttribLocation,
+    )
+}
+
+fn active_variables(
+    program: GLuint,
+    count: GLenum,
+    max_length: GLenum,
+    get_active: unsafe fn(
+        GLuint,
+        GLuint,
+        GLsizei,
+        *mut GLsizei,
+        *mut GLint,
+        *mut GLenum,
+        *mut GLchar,
+    ),
+    get_location: unsafe fn(GLuint, *const GLchar) -> GLint,
+) -> Vec<ShaderVariable> {
+    unsafe {
+        let mut n = 0;
+        gl::GetProgramiv(program, count, &mut n);
+        let mut len = 0;
+        gl::GetProgramiv(program, max_length, &mut len);
+
+        let mut buf = vec![0u8; len.max(1) as usize];
+        let mut variables = Vec::with_capacity(n as usize);
+        for i in 0..n as GLuint {
+            let mut written = 0;
+            let mut size = 0;
+            let mut ty = 0;
+            get_active(
+                program,
+                i,
+                buf.len() as GLsizei,
+                &mut written,
+                &mut size,
+                &mut ty,
+                buf.as_mut_ptr() as *mut GLchar,
+            );
+            let name = String::from_utf8_lossy(&buf[..written as usize]).into_owned();
+            if name.starts_with("gl_") {
+                continue;
+            }
+
+            let location = get_location(program, CString::new(name.clone()).unwrap().as_ptr());
+            let name = match name.find('[') {
+                Some(i) if name.ends_with("[0]") => name[..i].to_owned(),
+                _ => name,
+            };
+            variables.push(ShaderVariable {
+                name,
+                ty: ty.into(),
+                size,
+                location,
+            });
+        }
+        variables
+    }
+}
+
+/// An active uniform block of a linked program.
+#[derive(Debug, Clone)]
+pub struct UniformBlock {
+    pub name: String,
+    pub index: GLuint,
+    /// The minimum size in bytes of a buffer backing the block.
+    pub size: GLint,
+}
+
+pub fn active_uniform_blocks(program: GLuint) -> Vec<UniformBlock> {
+    unsafe {
+        let mut n = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut n);
+        let mut len = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH, &mut len);
+
+        let mut buf = vec![0u8; len.max(1) as usize];
+        (0..n as GLuint)
+            .map(|index| {
+                let mut written = 0;
+                gl::GetActiveUniformBlockName(
+                    program,
+                    index,
+                    buf.len() as GLsizei,
+                    &mut written,
+                    buf.as_mut_ptr() as *mut GLchar,
+                );
+                let mut size = 0;
+                gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+                UniformBlock {
+                    name: String::from_utf8_lossy(&buf[..written as usize]).into_owned(),
+                    index,
+                    size,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A disagreement between a vertex layout and the attributes a program expects.
+#[derive(Debug, Clone)]
+pub enum AttribMismatch {
+    /// The program reads an attribute location that the layout does not provide.
+    Missing { name: String, location: GLuint },
+    /// The layout feeds float data to an integer attribute or the other way around.
+    Integer {
+        name: String,
+        location: GLuint,
+        expected: bool,
+    },
+}
+
+impl fmt::Display for AttribMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AttribMismatch::Missing { ref name, location } => write!(
+                f,
+                "attribute `{}` at location {} has no vertex data",
+                name, location
+            ),
+            AttribMismatch::Integer {
+                ref name,
+                location,
+                expected,
+            } => write!(
+                f,
+                "attribute `{}` at location {} expects {} data",
+                name,
+                location,
+                if expected { "integer" } else { "float" }
+            ),
+        }
+    }
+}
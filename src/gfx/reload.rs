@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use gfx::preprocessor::Preprocessor;
+use gfx::shader::{ActiveProgram, Program, Shader, ShaderError, ShaderStage};
+
+use gl::types::*;
+
+/// A `Program` built from shader files on disk that is rebuilt whenever one of them, or a file
+/// they include, changes.
+pub struct ReloadableProgram {
+    preprocessor: Preprocessor,
+    sources: Vec<(ShaderStage, String)>,
+    watched: Vec<WatchedFile>,
+    program: Program,
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: PathBuf) -> Self {
+        WatchedFile {
+            modified: modified(&path),
+            path,
+        }
+    }
+}
+
+impl ReloadableProgram {
+    /// Builds a program from files relative to the preprocessor root.
+    pub fn new(
+        preprocessor: Preprocessor,
+        sources: &[(ShaderStage, &str)],
+    ) -> Result<Self, ShaderError> {
+        let sources: Vec<_> = sources
+            .iter()
+            .map(|&(stage, name)| (stage, name.to_owned()))
+            .collect();
+        let (program, files) = build(&preprocessor, &sources)?;
+        let watched = files
+            .into_iter()
+            .map(|f| WatchedFile::new(preprocessor.root().join(f)))
+            .collect();
+
+        Ok(ReloadableProgram {
+            preprocessor,
+            sources,
+            watched,
+            program,
+        })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn bind(&self) -> ActiveProgram<'_> {
+        self.program.bind()
+    }
+
+    /// Looks up a uniform location, remembering it until the next successful reload.
+    pub fn get_uniform_location(&self, name: &str) -> GLint {
+        self.program.uniform_location(name)
+    }
+
+    /// Rebuilds the program if any source file was modified since the last attempt.
+    ///
+    /// Returns whether the program was replaced. On failure the previous program is kept and
+    /// the files are not retried until they change again.
+    pub fn reload_if_changed(&mut self) -> Result<bool, ShaderError> {
+        let mut changed = false;
+        for file in &mut self.watched {
+            let modified = modified(&file.path);
+            if modified != file.modified {
+                file.modified = modified;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.reload().map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        let (program, files) = build(&self.preprocessor, &self.sources)?;
+        self.program = program;
+
+        // Pick up any includes that were added or removed
+        let root = self.preprocessor.root();
+        let mut watched = Vec::with_capacity(files.len());
+        for path in files.into_iter().map(|f| root.join(f)) {
+            match self.watched.iter().position(|w| w.path == path) {
+                Some(i) => watched.push(self.watched.swap_remove(i)),
+                None => watched.push(WatchedFile::new(path)),
+            }
+        }
+        self.watched = watched;
+        Ok(())
+    }
+}
+
+/// Builds the program, returning it along with every file it was built from.
+fn build(
+    preprocessor: &Preprocessor,
+    sources: &[(ShaderStage, String)],
+) -> Result<(Program, Vec<String>), ShaderError> {
+    let mut files = Vec::new();
+    let mut shaders = Vec::new();
+    for &(stage, ref name) in sources {
+        let source = preprocessor.process_file(name)?;
+        files.extend(source.line_map.files().iter().cloned());
+        shaders.push(Shader::compile_source(source.shader_source(stage))?);
+    }
+    files.sort();
+    files.dedup();
+    Ok((Program::from_shaders(&shaders)?, files))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
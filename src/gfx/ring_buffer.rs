@@ -0,0 +1,115 @@
+use std::mem;
+
+use gfx::buffer::{Buffer, BufferType, BufferUsage, MapAccess};
+use gfx::caps;
+use gfx::sync::Fence;
+
+/// A buffer for data that is rewritten every frame, such as debug lines or particles.
+///
+/// The buffer is split into one region per frame in flight. Each frame writes into its own
+/// region, and a fence stops a region from being overwritten while the GPU may still read it.
+/// Without `glBufferStorage` the buffer is a single region that gets orphaned every frame.
+pub struct RingBuffer {
+    buffer: Buffer,
+    mapping: Option<*mut u8>,
+    frame_size: usize,
+    fences: Vec<Option<Fence>>,
+    frame: usize,
+    offset: usize,
+}
+
+impl RingBuffer {
+    /// Creates a ring of `frames` regions of `frame_size` bytes each.
+    pub fn new(ty: BufferType, frame_size: usize, frames: usize) -> Self {
+        assert!(frames > 0);
+        let buffer = Buffer::with_usage(ty, BufferUsage::Stream);
+
+        let mapping = if caps::buffer_storage() {
+            let flags = MapAccess::WRITE | MapAccess::PERSISTENT | MapAccess::COHERENT;
+            let mut active = buffer.bind();
+            active.storage(frame_size * frames, flags);
+            let mut mapped = active.map_range(0, frame_size * frames, flags);
+            let ptr = mapped.as_mut_ptr();
+            // The buffer stays mapped for as long as it exists
+            mem::forget(mapped);
+            Some(ptr)
+        } else {
+            buffer.bind().allocate(frame_size);
+            None
+        };
+
+        RingBuffer {
+            buffer,
+            mapping,
+            frame_size,
+            fences: (0..frames).map(|_| None).collect(),
+            frame: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Whether the buffer is persistently mapped rather than orphaned each frame.
+    pub fn is_persistent(&self) -> bool {
+        self.mapping.is_some()
+    }
+
+    /// Moves on to the next region, waiting for the GPU to finish with it if necessary.
+    pub fn begin_frame(&mut self) {
+        self.offset = 0;
+        if self.mapping.is_none() {
+            self.buffer.bind().allocate(self.frame_size);
+            return;
+        }
+
+        self.frame = (self.frame + 1) % self.fences.len();
+        if let Some(fence) = self.fences[self.frame].take() {
+            while !fence.wait(1_000_000_000) {}
+        }
+    }
+
+    /// Fences the current region. Call once all draws reading from it have been issued.
+    pub fn end_frame(&mut self) {
+        if self.mapping.is_some() {
+            self.fences[self.frame] = Some(Fence::new());
+        }
+    }
+
+    /// Copies `data` into the current region at a multiple of `align` and returns its byte
+    /// offset within `buffer()`.
+    pub fn push(&mut self, data: &[u8], align: usize) -> usize {
+        assert!(align > 0, "ring buffer alignment must be at least 1");
+        // Align the offset within the whole buffer, as `frame_size` need not be a multiple of
+        // `align`
+        let base = match self.mapping {
+            Some(_) => self.frame * self.frame_size,
+            None => 0,
+        };
+        let start = (base + self.offset).div_ceil(align) * align - base;
+        assert!(
+            start + data.len() <= self.frame_size,
+            "ring buffer frame overflow: {} bytes requested, {} left",
+            data.len(),
+            self.frame_size.saturating_sub(start)
+        );
+        self.offset = start + data.len();
+
+        match self.mapping {
+            Some(ptr) => {
+                let offset = base + start;
+                unsafe {
+                    ptr.add(offset)
+                        .copy_from_nonoverlapping(data.as_ptr(), data.len())
+                }
+                offset
+            }
+            None => {
+                self.buffer.bind().sub_data(start, data);
+                start
+            }
+        }
+    }
+}
@@ -1,62 +1,153 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
 use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::ops::BitOr;
+use std::path::PathBuf;
 use std::ptr;
-use std::str;
 
-use cgmath::{Matrix, Matrix4};
+use gfx::preprocessor::LineMap;
+use gfx::reflection::{self, AttribMismatch, GlslType, ShaderVariable, UniformBlock};
+use gfx::vertex_array::VertexAttrib;
 use gl;
 use gl::types::*;
 
-pub enum UniformValue {
-    Matrix4(Matrix4<f32>),
-    I1(i32),
-}
+pub use gfx::uniform::UniformValue;
 
 pub struct Program {
     pub id: GLuint,
+    stages: Vec<ShaderStage>,
+    uniforms: Vec<ShaderVariable>,
+    attributes: Vec<ShaderVariable>,
+    uniform_blocks: Vec<UniformBlock>,
+    uniform_cache: RefCell<HashMap<String, CachedUniform>>,
+}
+
+/// A uniform looked up by name, along with the value last uploaded through `set`.
+struct CachedUniform {
+    location: GLint,
+    value: Option<UniformValue<'static>>,
 }
 
 impl Program {
-    pub fn from_shaders<'a, I>(shaders: I) -> Self
+    pub fn from_shaders<'a, I>(shaders: I) -> Result<Self, ShaderError>
+    where
+        I: IntoIterator<Item = &'a Shader>,
+    {
+        Self::link(shaders, false)
+    }
+
+    /// Compiles and links a program in one step.
+    pub fn from_sources(sources: &[ShaderSource]) -> Result<Self, ShaderError> {
+        let shaders = sources
+            .iter()
+            .map(|&s| Shader::compile_source(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_shaders(&shaders)
+    }
+
+    /// Like `from_shaders`, but tells the driver that `binary` will be called on the result.
+    pub fn from_shaders_retrievable<'a, I>(shaders: I) -> Result<Self, ShaderError>
     where
         I: IntoIterator<Item = &'a Shader>,
     {
-        let program = unsafe {
+        Self::link(shaders, true)
+    }
+
+    /// Recreates a program from the output of `binary`. Drivers reject binaries from other
+    /// driver versions or hardware, in which case the program has to be built from source.
+    pub fn from_binary(
+        format: GLenum,
+        binary: &[u8],
+        stages: &[ShaderStage],
+    ) -> Result<Self, ShaderError> {
+        unsafe {
+            let program = gl::CreateProgram();
+            gl::ProgramBinary(
+                program,
+                format,
+                binary.as_ptr() as *const _,
+                binary.len() as GLsizei,
+            );
+            Self::from_linked(program, stages.to_vec())
+        }
+    }
+
+    fn link<'a, I>(shaders: I, retrievable: bool) -> Result<Self, ShaderError>
+    where
+        I: IntoIterator<Item = &'a Shader>,
+    {
+        let mut stages = Vec::new();
+        unsafe {
             let program = gl::CreateProgram();
             for shader in shaders {
                 gl::AttachShader(program, shader.id);
+                stages.push(shader.stage);
+            }
+            if retrievable {
+                gl::ProgramParameteri(
+                    program,
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as GLint,
+                );
             }
             gl::LinkProgram(program);
+            Self::from_linked(program, stages)
+        }
+    }
 
-            // Get the link status
-            let mut status = gl::FALSE as GLint;
-            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    unsafe fn from_linked(program: GLuint, stages: Vec<ShaderStage>) -> Result<Self, ShaderError> {
+        // Get the link status
+        let mut status = gl::FALSE as GLint;
+        gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
 
-            // Fail on error
-            if status != (gl::TRUE as GLint) {
-                let mut len: GLint = 0;
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
-                gl::GetProgramInfoLog(
-                    program,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut GLchar,
-                );
-                panic!(
-                    "{}",
-                    str::from_utf8(&buf)
-                        .ok()
-                        .expect("ProgramInfoLog not valid utf8",)
-                );
+        // Fail on error
+        if status != (gl::TRUE as GLint) {
+            let log = program_info_log(program);
+            gl::DeleteProgram(program);
+            return Err(ShaderError::Link {
+                diagnostics: Diagnostic::parse_log(&log, None),
+                log,
+            });
+        }
+
+        Ok(Program {
+            id: program,
+            stages,
+            uniforms: reflection::active_uniforms(program),
+            attributes: reflection::active_attributes(program),
+            uniform_blocks: reflection::active_uniform_blocks(program),
+            uniform_cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Retrieves the linked program in the driver's binary format, if it supports any.
+    pub fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        unsafe {
+            let mut len = 0;
+            gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut len);
+            if len <= 0 {
+                return None;
             }
-            program
-        };
 
-        Program { id: program }
+            let mut binary = vec![0u8; len as usize];
+            let mut written = 0;
+            let mut format = 0;
+            gl::GetProgramBinary(
+                self.id,
+                len,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+            binary.truncate(written as usize);
+            Some((format, binary))
+        }
     }
 
-    pub fn bind(&self) -> ActiveProgram {
+    pub fn bind(&self) -> ActiveProgram<'_> {
         ActiveProgram::new(self)
     }
 
@@ -66,6 +157,109 @@ impl Program {
             gl::GetUniformLocation(self.id, c_str.as_ptr())
         }
     }
+
+    /// Like `get_uniform_location`, but remembers the result. Warns once about names that are
+    /// not active uniforms.
+    pub fn uniform_location(&self, name: &str) -> GLint {
+        let mut cache = self.uniform_cache.borrow_mut();
+        if let Some(cached) = cache.get(name) {
+            return cached.location;
+        }
+
+        let location = self.get_uniform_location(name);
+        if location < 0 {
+            eprintln!(
+                "warning: program {} has no active uniform `{}`",
+                self.id, name
+            );
+        }
+        cache.insert(
+            name.to_owned(),
+            CachedUniform {
+                location,
+                value: None,
+            },
+        );
+        location
+    }
+
+    pub fn stages(&self) -> &[ShaderStage] {
+        &self.stages
+    }
+
+    pub fn uniforms(&self) -> &[ShaderVariable] {
+        &self.uniforms
+    }
+
+    pub fn attributes(&self) -> &[ShaderVariable] {
+        &self.attributes
+    }
+
+    pub fn uniform_info(&self, name: &str) -> Option<&ShaderVariable> {
+        self.uniforms.iter().find(|u| u.name == name)
+    }
+
+    /// The local work group size of a compute program.
+    pub fn work_group_size(&self) -> Option<[GLint; 3]> {
+        if !self.stages.contains(&ShaderStage::Compute) {
+            return None;
+        }
+
+        let mut size = [0; 3];
+        unsafe { gl::GetProgramiv(self.id, gl::COMPUTE_WORK_GROUP_SIZE, size.as_mut_ptr()) }
+        Some(size)
+    }
+
+    pub fn uniform_blocks(&self) -> &[UniformBlock] {
+        &self.uniform_blocks
+    }
+
+    /// Points the named uniform block at a binding point, so that it reads from whichever
+    /// buffer is bound there with `Buffer::bind_base`. Returns false if the block is not active.
+    pub fn bind_uniform_block(&self, name: &str, binding: GLuint) -> bool {
+        match self.uniform_blocks.iter().find(|b| b.name == name) {
+            Some(block) => {
+                unsafe { gl::UniformBlockBinding(self.id, block.index, binding) }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks that a vertex layout feeds every attribute the program reads, with matching
+    /// integer-ness. Matrix attributes expect one entry per column.
+    ///
+    /// Component counts are not compared, as GL fills missing components from `(0, 0, 0, 1)`
+    /// and ignores extra ones.
+    pub fn validate_attribs(&self, attribs: &[VertexAttrib]) -> Result<(), Vec<AttribMismatch>> {
+        let mut mismatches = Vec::new();
+        for attribute in &self.attributes {
+            let (columns, _) = attribute.ty.attrib_shape();
+            for i in 0..columns * attribute.size {
+                let location = (attribute.location + i) as GLuint;
+                match attribs.iter().find(|a| a.location == location) {
+                    Some(a) if a.integer != attribute.ty.is_integer() => {
+                        mismatches.push(AttribMismatch::Integer {
+                            name: attribute.name.clone(),
+                            location,
+                            expected: attribute.ty.is_integer(),
+                        })
+                    }
+                    Some(_) => (),
+                    None => mismatches.push(AttribMismatch::Missing {
+                        name: attribute.name.clone(),
+                        location,
+                    }),
+                }
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
 }
 
 impl Drop for Program {
@@ -86,16 +280,101 @@ impl<'a> ActiveProgram<'a> {
         ActiveProgram { program }
     }
 
-    pub fn uniform(&self, location: GLint, val: UniformValue) {
-        unsafe {
-            match val {
-                UniformValue::Matrix4(mat4) => {
-                    gl::UniformMatrix4fv(location, 1, gl::FALSE, mat4.as_ptr())
-                }
-                UniformValue::I1(i) => gl::Uniform1i(location, i),
+    pub fn uniform<'v, V>(&self, location: GLint, val: V)
+    where
+        V: Into<UniformValue<'v>>,
+    {
+        let val = val.into();
+        if cfg!(debug_assertions) {
+            self.check_uniform(location, &val);
+        }
+        val.upload(location);
+
+        // Whatever `set` last uploaded to the written locations is stale now
+        self.invalidate(location, val.len());
+    }
+
+    /// Sets a uniform by name. The location is looked up once per program, unknown names are
+    /// ignored, and nothing is uploaded if a non-array value is unchanged since the last `set`.
+    pub fn set<'v, V>(&self, name: &str, val: V)
+    where
+        V: Into<UniformValue<'v>>,
+    {
+        let location = self.program.uniform_location(name);
+        if location < 0 {
+            return;
+        }
+
+        let val = val.into();
+        let value = val.to_static();
+        if value.is_some() && self.program.uniform_cache.borrow()[name].value == value {
+            return;
+        }
+        if cfg!(debug_assertions) {
+            self.check_uniform(location, &val);
+        }
+        val.upload(location);
+        self.invalidate(location, val.len());
+        self.program
+            .uniform_cache
+            .borrow_mut()
+            .get_mut(name)
+            .unwrap()
+            .value = value;
+    }
+
+    /// Forgets the cached values of uniforms in the `len` locations starting at `location`.
+    fn invalidate(&self, location: GLint, len: usize) {
+        let end = location + len as GLint;
+        for cached in self.program.uniform_cache.borrow_mut().values_mut() {
+            if cached.location >= location && cached.location < end {
+                cached.value = None;
             }
         }
     }
+
+    /// Launches a compute program over `x * y * z` work groups.
+    pub fn dispatch(&self, x: GLuint, y: GLuint, z: GLuint) {
+        debug_assert!(
+            self.program.stages.contains(&ShaderStage::Compute),
+            "dispatch on a program without a compute shader"
+        );
+        unsafe { gl::DispatchCompute(x, y, z) }
+    }
+
+    /// Sets the number of vertices per patch read by tessellation programs.
+    pub fn set_patch_vertices(&self, count: GLint) {
+        unsafe { gl::PatchParameteri(gl::PATCH_VERTICES, count) }
+    }
+
+    /// Panics if `val` does not fit the reflected type of the uniform at `location`.
+    fn check_uniform(&self, location: GLint, val: &UniformValue) {
+        if location < 0 {
+            return;
+        }
+        let info =
+            self.program.uniforms.iter().find(|u| {
+                u.location >= 0 && location >= u.location && location < u.location + u.size
+            });
+        if let Some(info) = info {
+            // Types without their own `GlslType` variant can't be checked
+            assert!(
+                matches!(info.ty, GlslType::Other(_)) || val.matches(info.ty),
+                "uniform `{}` is {:?} but was given {:?}",
+                info.name,
+                info.ty,
+                val.glsl_type()
+            );
+            let remaining = (info.size - (location - info.location)) as usize;
+            assert!(
+                val.len() <= remaining,
+                "uniform `{}` has room for {} elements but was given {}",
+                info.name,
+                remaining,
+                val.len()
+            );
+        }
+    }
 }
 
 impl<'a> Drop for ActiveProgram<'a> {
@@ -104,17 +383,54 @@ impl<'a> Drop for ActiveProgram<'a> {
     }
 }
 
+/// The kinds of incoherent shader writes that `memory_barrier` makes visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBarrier(GLbitfield);
+
+impl MemoryBarrier {
+    pub const VERTEX_ATTRIB_ARRAY: MemoryBarrier =
+        MemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: MemoryBarrier = MemoryBarrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: MemoryBarrier = MemoryBarrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: MemoryBarrier = MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: MemoryBarrier =
+        MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND: MemoryBarrier = MemoryBarrier(gl::COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER: MemoryBarrier = MemoryBarrier(gl::PIXEL_BUFFER_BARRIER_BIT);
+    pub const TEXTURE_UPDATE: MemoryBarrier = MemoryBarrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE: MemoryBarrier = MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER: MemoryBarrier = MemoryBarrier(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const TRANSFORM_FEEDBACK: MemoryBarrier = MemoryBarrier(gl::TRANSFORM_FEEDBACK_BARRIER_BIT);
+    pub const ATOMIC_COUNTER: MemoryBarrier = MemoryBarrier(gl::ATOMIC_COUNTER_BARRIER_BIT);
+    pub const SHADER_STORAGE: MemoryBarrier = MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const ALL: MemoryBarrier = MemoryBarrier(gl::ALL_BARRIER_BITS);
+}
+
+impl BitOr for MemoryBarrier {
+    type Output = MemoryBarrier;
+
+    fn bitor(self, other: MemoryBarrier) -> MemoryBarrier {
+        MemoryBarrier(self.0 | other.0)
+    }
+}
+
+/// Orders shader writes before later commands that read them, e.g. between a compute
+/// dispatch and a draw that sources its output.
+pub fn memory_barrier(barrier: MemoryBarrier) {
+    unsafe { gl::MemoryBarrier(barrier.0) }
+}
+
 pub struct Shader {
     stage: ShaderStage,
     pub id: GLuint,
 }
 
 impl Shader {
-    pub fn from_source(stage: ShaderStage, source: &str) -> Self {
+    pub fn from_source(stage: ShaderStage, source: &str) -> Result<Self, ShaderError> {
         Self::compile_source(ShaderSource::new(stage, source))
     }
 
-    pub fn compile_source<'a>(source: ShaderSource<'a>) -> Self {
+    pub fn compile_source(source: ShaderSource) -> Result<Self, ShaderError> {
         let id = unsafe {
             let shader = gl::CreateShader(source.stage.into());
             let c_str = CString::new(source.src.as_bytes()).unwrap();
@@ -126,29 +442,24 @@ impl Shader {
 
             // Fail on error
             if status != (gl::TRUE as GLint) {
-                let mut len = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
-                gl::GetShaderInfoLog(
-                    shader,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut GLchar,
-                );
-                panic!(
-                    "{}",
-                    str::from_utf8(&buf)
-                        .ok()
-                        .expect("ShaderInfoLog not valid utf8",)
-                );
+                let log = shader_info_log(shader);
+                gl::DeleteShader(shader);
+                return Err(ShaderError::Compile {
+                    stage: source.stage,
+                    diagnostics: Diagnostic::parse_log(&log, Some(&source)),
+                    log,
+                });
             }
             shader
         };
-        Shader {
+        Ok(Shader {
             stage: source.stage,
-            id: id,
-        }
+            id,
+        })
+    }
+
+    pub fn stage(&self) -> ShaderStage {
+        self.stage
     }
 }
 
@@ -160,31 +471,344 @@ impl Drop for Shader {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct ShaderSource<'a> {
     stage: ShaderStage,
     src: &'a str,
+    line_map: Option<&'a LineMap>,
 }
 
 impl<'a> ShaderSource<'a> {
     pub fn new(stage: ShaderStage, src: &'a str) -> Self {
         ShaderSource {
-            stage: stage,
-            src: src,
+            stage,
+            src,
+            line_map: None,
         }
     }
+
+    /// Attaches a line map so compiler diagnostics point into the original files.
+    pub fn with_line_map(mut self, line_map: &'a LineMap) -> Self {
+        self.line_map = Some(line_map);
+        self
+    }
+
+    pub fn stage(&self) -> ShaderStage {
+        self.stage
+    }
+
+    pub fn src(&self) -> &'a str {
+        self.src
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShaderStage {
     Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
     Fragment,
+    Compute,
 }
 
 impl From<ShaderStage> for GLenum {
     fn from(stage: ShaderStage) -> Self {
         match stage {
             ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
             ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::TessControl => "tessellation control",
+            ShaderStage::TessEvaluation => "tessellation evaluation",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Compute => "compute",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Compile {
+        stage: ShaderStage,
+        log: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Link {
+        log: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Preprocess {
+        file: String,
+        line: u32,
+        message: String,
+    },
+}
+
+impl ShaderError {
+    pub fn log(&self) -> &str {
+        match *self {
+            ShaderError::Compile { ref log, .. } | ShaderError::Link { ref log, .. } => log,
+            ShaderError::Io { .. } | ShaderError::Preprocess { .. } => "",
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match *self {
+            ShaderError::Compile {
+                ref diagnostics, ..
+            }
+            | ShaderError::Link {
+                ref diagnostics, ..
+            } => diagnostics,
+            ShaderError::Io { .. } | ShaderError::Preprocess { .. } => &[],
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderError::Compile { stage, .. } => {
+                writeln!(f, "failed to compile {} shader", stage)?
+            }
+            ShaderError::Link { .. } => writeln!(f, "failed to link program")?,
+            ShaderError::Io {
+                ref path,
+                ref error,
+            } => return write!(f, "failed to read {}: {}", path.display(), error),
+            ShaderError::Preprocess {
+                ref file,
+                line,
+                ref message,
+            } => return write!(f, "{}:{}: {}", file, line, message),
         }
+        for d in self.diagnostics() {
+            writeln!(f, "{}", d)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ShaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ShaderError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A single message from a shader compiler or linker log.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The file the message refers to, when the source came from the preprocessor.
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+    /// The offending line of the original source, if known.
+    pub excerpt: Option<String>,
+}
+
+impl Diagnostic {
+    fn parse_log(log: &str, source: Option<&ShaderSource>) -> Vec<Self> {
+        log.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| Self::parse(l, source))
+            .collect()
+    }
+
+    fn parse(line: &str, source: Option<&ShaderSource>) -> Self {
+        match parse_location(line) {
+            Some((line, column, message)) => {
+                let excerpt = source
+                    .and_then(|s| s.src.lines().nth((line as usize).wrapping_sub(1)))
+                    .map(|s| s.to_owned());
+                let (file, line) = match source.and_then(|s| s.line_map) {
+                    Some(map) => match map.resolve(line) {
+                        Some((file, line)) => (Some(file.to_owned()), line),
+                        None => (None, line),
+                    },
+                    None => (None, line),
+                };
+                Diagnostic {
+                    file,
+                    line: Some(line),
+                    column,
+                    message,
+                    excerpt,
+                }
+            }
+            None => Diagnostic {
+                file: None,
+                line: None,
+                column: None,
+                message: line.to_owned(),
+                excerpt: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}:", file)?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: {}", line, column, self.message)?,
+            (Some(line), None) => write!(f, "{}: {}", line, self.message)?,
+            _ => write!(f, "{}", self.message)?,
+        }
+        if let Some(ref excerpt) = self.excerpt {
+            write!(f, "\n    | {}", excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a driver log line into its line, column and message.
+///
+/// Understands the Mesa (`0:12(5): error: ...`), NVIDIA (`0(12) : error C0000: ...`) and
+/// AMD/Intel (`ERROR: 0:12: ...`) formats.
+fn parse_location(line: &str) -> Option<(u32, Option<u32>, String)> {
+    let (severity, rest) = if let Some(rest) = line.strip_prefix("ERROR: ") {
+        (Some("error"), rest)
+    } else if let Some(rest) = line.strip_prefix("WARNING: ") {
+        (Some("warning"), rest)
+    } else {
+        (None, line)
+    };
+
+    // Skip the source string index
+    let (_, rest) = split_number(rest)?;
+    if let Some(rest) = rest.strip_prefix(':') {
+        let (line, rest) = split_number(rest)?;
+        if let Some(rest) = rest.strip_prefix('(') {
+            let (column, rest) = split_number(rest)?;
+            let message = rest.strip_prefix("):")?.trim();
+            Some((line, Some(column), message.to_owned()))
+        } else {
+            let message = rest.strip_prefix(':')?.trim();
+            let message = match severity {
+                Some(severity) => format!("{}: {}", severity, message),
+                None => message.to_owned(),
+            };
+            Some((line, None, message))
+        }
+    } else if let Some(rest) = rest.strip_prefix('(') {
+        let (line, rest) = split_number(rest)?;
+        let message = rest
+            .strip_prefix(')')?
+            .trim_start()
+            .strip_prefix(':')?
+            .trim();
+        Some((line, None, message.to_owned()))
+    } else {
+        None
+    }
+}
+
+fn split_number(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok().map(|n| (n, &s[end..]))
+}
+
+fn shader_info_log(shader: GLuint) -> String {
+    unsafe {
+        let mut len = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+        let mut buf = vec![0u8; len.max(1) as usize];
+        let mut written = 0;
+        gl::GetShaderInfoLog(
+            shader,
+            buf.len() as GLsizei,
+            &mut written,
+            buf.as_mut_ptr() as *mut GLchar,
+        );
+        buf.truncate(written as usize);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+fn program_info_log(program: GLuint) -> String {
+    unsafe {
+        let mut len = 0;
+        gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+        let mut buf = vec![0u8; len.max(1) as usize];
+        let mut written = 0;
+        gl::GetProgramInfoLog(
+            program,
+            buf.len() as GLsizei,
+            &mut written,
+            buf.as_mut_ptr() as *mut GLchar,
+        );
+        buf.truncate(written as usize);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mesa_locations() {
+        assert_eq!(
+            parse_location("0:12(5): error: `foo' undeclared"),
+            Some((12, Some(5), "error: `foo' undeclared".to_owned()))
+        );
+    }
+
+    #[test]
+    fn parses_nvidia_locations() {
+        assert_eq!(
+            parse_location("0(12) : error C1008: undefined variable \"foo\""),
+            Some((
+                12,
+                None,
+                "error C1008: undefined variable \"foo\"".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_amd_intel_locations() {
+        assert_eq!(
+            parse_location("ERROR: 0:12: 'foo' : undeclared identifier"),
+            Some((12, None, "error: 'foo' : undeclared identifier".to_owned()))
+        );
+        assert_eq!(
+            parse_location("WARNING: 0:3: extension not supported"),
+            Some((3, None, "warning: extension not supported".to_owned()))
+        );
+    }
+
+    #[test]
+    fn ignores_lines_without_a_location() {
+        assert_eq!(
+            parse_location("ERROR: 1 compilation errors.  No code generated."),
+            None
+        );
+        assert_eq!(parse_location("Vertex shader failed to compile"), None);
+        assert_eq!(parse_location(""), None);
     }
 }
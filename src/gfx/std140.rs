@@ -0,0 +1,254 @@
+use cgmath::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+/// A type with a std140 layout.
+pub trait Std140 {
+    /// The base alignment in bytes.
+    const ALIGN: usize;
+
+    /// Appends the value's bytes, including any padding inside it.
+    fn write_std140(&self, out: &mut Vec<u8>);
+}
+
+/// Builds the contents of a std140 uniform block, inserting the padding the layout rules
+/// require between members.
+#[derive(Debug, Clone, Default)]
+pub struct Std140Writer {
+    data: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Self {
+        Std140Writer { data: Vec::new() }
+    }
+
+    pub fn write<T: Std140>(&mut self, value: &T) -> &mut Self {
+        self.align(T::ALIGN);
+        value.write_std140(&mut self.data);
+        self
+    }
+
+    /// Writes an array, whose elements are each rounded up to a multiple of 16 bytes.
+    pub fn write_array<T: Std140>(&mut self, values: &[T]) -> &mut Self {
+        self.align(16);
+        for value in values {
+            value.write_std140(&mut self.data);
+            self.align(16);
+        }
+        self
+    }
+
+    /// Starts a nested struct member, which is aligned to 16 bytes.
+    pub fn begin_struct(&mut self) -> &mut Self {
+        self.align(16);
+        self
+    }
+
+    /// Ends a nested struct member, padding it out to a multiple of 16 bytes.
+    pub fn end_struct(&mut self) -> &mut Self {
+        self.align(16);
+        self
+    }
+
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Empties the writer so its allocation can be reused for the next frame.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    fn align(&mut self, align: usize) {
+        let len = self.data.len().div_ceil(align) * align;
+        self.data.resize(len, 0);
+    }
+}
+
+fn write_column(out: &mut Vec<u8>, column: &[f32]) {
+    for c in column {
+        out.extend_from_slice(&c.to_ne_bytes());
+    }
+    out.resize(out.len() + 16 - column.len() * 4, 0);
+}
+
+impl Std140 for f32 {
+    const ALIGN: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for i32 {
+    const ALIGN: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for u32 {
+    const ALIGN: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_ne_bytes());
+    }
+}
+
+impl Std140 for bool {
+    const ALIGN: usize = 4;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        (*self as u32).write_std140(out);
+    }
+}
+
+impl<T: Std140> Std140 for Vector2<T> {
+    const ALIGN: usize = 2 * T::ALIGN;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.x.write_std140(out);
+        self.y.write_std140(out);
+    }
+}
+
+impl<T: Std140> Std140 for Vector3<T> {
+    const ALIGN: usize = 4 * T::ALIGN;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.x.write_std140(out);
+        self.y.write_std140(out);
+        self.z.write_std140(out);
+    }
+}
+
+impl<T: Std140> Std140 for Vector4<T> {
+    const ALIGN: usize = 4 * T::ALIGN;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        self.x.write_std140(out);
+        self.y.write_std140(out);
+        self.z.write_std140(out);
+        self.w.write_std140(out);
+    }
+}
+
+// Matrices are laid out as arrays of column vectors, so every column takes 16 bytes
+
+impl Std140 for Matrix2<f32> {
+    const ALIGN: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let columns: &[[f32; 2]; 2] = self.as_ref();
+        for column in columns {
+            write_column(out, column);
+        }
+    }
+}
+
+impl Std140 for Matrix3<f32> {
+    const ALIGN: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let columns: &[[f32; 3]; 3] = self.as_ref();
+        for column in columns {
+            write_column(out, column);
+        }
+    }
+}
+
+impl Std140 for Matrix4<f32> {
+    const ALIGN: usize = 16;
+
+    fn write_std140(&self, out: &mut Vec<u8>) {
+        let columns: &[[f32; 4]; 4] = self.as_ref();
+        for column in columns {
+            write_column(out, column);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn float_packs_after_vec3() {
+        let mut writer = Std140Writer::new();
+        writer.write(&Vector3::new(1.0f32, 2.0, 3.0));
+        assert_eq!(writer.offset(), 12);
+        writer.write(&4.0f32);
+        assert_eq!(writer.offset(), 16);
+        assert_eq!(floats(writer.as_bytes()), [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn vec3_after_float_is_aligned_to_16() {
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32).write(&Vector3::new(2.0f32, 3.0, 4.0));
+        assert_eq!(writer.offset(), 28);
+        assert_eq!(
+            floats(writer.as_bytes()),
+            [1.0, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn array_elements_have_16_byte_stride() {
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32).write_array(&[2.0f32, 3.0]);
+        assert_eq!(writer.offset(), 48);
+        let data = floats(writer.as_bytes());
+        assert_eq!(data[4], 2.0);
+        assert_eq!(data[8], 3.0);
+
+        writer.write(&4.0f32);
+        assert_eq!(writer.offset(), 52);
+    }
+
+    #[test]
+    fn mat3_columns_are_padded() {
+        let mut writer = Std140Writer::new();
+        #[rustfmt::skip]
+        let matrix = Matrix3::new(
+            1.0f32, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 9.0,
+        );
+        writer.write(&matrix);
+        assert_eq!(writer.offset(), 48);
+        #[rustfmt::skip]
+        assert_eq!(
+            floats(writer.as_bytes()),
+            [
+                1.0, 2.0, 3.0, 0.0,
+                4.0, 5.0, 6.0, 0.0,
+                7.0, 8.0, 9.0, 0.0,
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_struct_is_aligned_and_padded() {
+        let mut writer = Std140Writer::new();
+        writer
+            .write(&1.0f32)
+            .begin_struct()
+            .write(&Vector2::new(2.0f32, 3.0));
+        assert_eq!(writer.offset(), 24);
+        writer.end_struct().write(&4.0f32);
+        assert_eq!(writer.offset(), 36);
+        assert_eq!(floats(writer.as_bytes())[8], 4.0);
+    }
+}
@@ -0,0 +1,42 @@
+use gl;
+use gl::types::*;
+
+/// A fence inserted into the command stream, signaled once the GPU has executed every command
+/// issued before it.
+#[derive(Debug)]
+pub struct Fence {
+    sync: GLsync,
+}
+
+impl Fence {
+    pub fn new() -> Self {
+        Fence {
+            sync: unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) },
+        }
+    }
+
+    /// Blocks for at most `timeout` nanoseconds. Returns whether the fence was signaled.
+    pub fn wait(&self, timeout: u64) -> bool {
+        match unsafe { gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout) } {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => true,
+            gl::TIMEOUT_EXPIRED => false,
+            _ => panic!("glClientWaitSync failed"),
+        }
+    }
+
+    pub fn is_signaled(&self) -> bool {
+        self.wait(0)
+    }
+}
+
+impl Default for Fence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteSync(self.sync) }
+    }
+}
@@ -1,33 +1,80 @@
-use std::mem;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+
+use gfx::caps;
+use gfx::mipmap;
 
 use gl;
 use gl::types::*;
-use image::{ColorType, DecodingResult, ImageDecoder};
+use image::{ColorType, DecodingResult, ImageDecoder, ImageError};
+
+thread_local! {
+    // Which units are taken in the context current on this thread, sized on first use
+    static TEXTURE_UNITS: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+}
 
-static mut TEXTURE_UNITS: [bool; 256] = [false; 256];
+fn with_units<R, F: FnOnce(&mut Vec<bool>) -> R>(f: F) -> R {
+    TEXTURE_UNITS.with(|units| {
+        let mut units = units.borrow_mut();
+        if units.is_empty() {
+            units.resize(max_texture_units() as usize, false);
+        }
+        f(&mut units)
+    })
+}
 
+/// The number of texture units usable across all shader stages.
+pub fn max_texture_units() -> u32 {
+    let mut max = 0;
+    unsafe { gl::GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut max) }
+    max as u32
+}
+
+/// A reserved texture unit, released again when dropped.
 pub struct TextureUnit {
     id: u32,
     bound_texture: Option<Texture>,
 }
 
 impl TextureUnit {
+    /// Reserves unit `id`. Returns `None` if it is taken or beyond the implementation's limit.
     pub fn take(id: u32) -> Option<Self> {
-        if unsafe { TEXTURE_UNITS[id as usize] } {
-            None
-        } else {
+        let free = with_units(|units| match units.get_mut(id as usize) {
+            Some(taken) if !*taken => {
+                *taken = true;
+                true
+            }
+            _ => false,
+        });
+        if free {
             Some(TextureUnit {
                 id,
                 bound_texture: None,
             })
+        } else {
+            None
         }
     }
 
+    /// Reserves the lowest free unit.
+    pub fn take_any() -> Option<Self> {
+        let id = with_units(|units| {
+            let id = units.iter().position(|&taken| !taken)?;
+            units[id] = true;
+            Some(id as u32)
+        })?;
+        Some(TextureUnit {
+            id,
+            bound_texture: None,
+        })
+    }
+
     pub fn replace_texture(&mut self, tex: Texture) -> Option<Texture> {
-        mem::replace(&mut self.bound_texture, Some(tex))
+        self.bound_texture.replace(tex)
     }
 
-    pub fn bind_texture(&self) -> Option<ActiveTexture> {
+    pub fn bind_texture(&self) -> Option<ActiveTexture<'_>> {
         unsafe { gl::ActiveTexture(gl::TEXTURE0 + self.id) }
         self.bound_texture.as_ref().map(|t| t.bind())
     }
@@ -37,25 +84,50 @@ impl TextureUnit {
     }
 }
 
+impl Drop for TextureUnit {
+    fn drop(&mut self) {
+        // Don't size the table here: a unit dropped on a thread that never took one has no slot
+        TEXTURE_UNITS.with(|units| {
+            if let Some(taken) = units.borrow_mut().get_mut(self.id as usize) {
+                *taken = false;
+            }
+        });
+    }
+}
+
 pub struct Texture {
     id: GLuint,
+    target: TextureTarget,
 }
 
 impl Texture {
     pub fn new() -> Self {
-        let texture = unsafe {
+        Self::with_target(TextureTarget::Texture2D)
+    }
+
+    pub fn with_target(target: TextureTarget) -> Self {
+        unsafe {
             let mut id = 0;
             gl::GenTextures(1, &mut id);
-            Texture { id }
-        };
-        texture
+            Texture { id, target }
+        }
+    }
+
+    pub fn target(&self) -> TextureTarget {
+        self.target
     }
 
-    fn bind(&self) -> ActiveTexture {
+    fn bind(&self) -> ActiveTexture<'_> {
         ActiveTexture::new(self)
     }
 }
 
+impl Default for Texture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for Texture {
     fn drop(&mut self) {
         unsafe { gl::DeleteTextures(1, &self.id) }
@@ -68,59 +140,858 @@ pub struct ActiveTexture<'a> {
 
 impl<'a> ActiveTexture<'a> {
     fn new(texture: &'a Texture) -> Self {
-        unsafe { gl::BindTexture(gl::TEXTURE_2D, texture.id) }
+        unsafe { gl::BindTexture(texture.target.into(), texture.id) }
         ActiveTexture { texture }
     }
 
-    pub fn set_wrap_function(&self, wrap: (WrapFunction, WrapFunction)) {
+    fn target(&self) -> GLenum {
+        self.texture.target.into()
+    }
+
+    /// Sets the wrap functions from a `(s, t)` or `(s, t, r)` tuple. The `r` axis applies to 3D
+    /// textures and cube maps. Rectangle textures can only clamp.
+    pub fn set_wrap_function<W: Into<Wrap>>(&self, wrap: W) {
+        let wrap = wrap.into();
+        assert!(
+            !self.is_rectangle()
+                || [Some(wrap.s), Some(wrap.t), wrap.r]
+                    .iter()
+                    .all(|&w| w.is_none() || w == Some(WrapFunction::Clamp)),
+            "rectangle textures can only use WrapFunction::Clamp"
+        );
         unsafe {
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap.0.into());
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap.1.into());
+            gl::TexParameteri(self.target(), gl::TEXTURE_WRAP_S, wrap.s.into());
+            gl::TexParameteri(self.target(), gl::TEXTURE_WRAP_T, wrap.t.into());
+            if let Some(r) = wrap.r {
+                gl::TexParameteri(self.target(), gl::TEXTURE_WRAP_R, r.into());
+            }
         }
     }
 
+    /// Sets the minifying filter. Rectangle textures have no mip levels to filter between.
     pub fn set_minify_filter(&self, filter: MinifyFilter) {
-        unsafe { gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter.into()) }
+        assert!(
+            !self.is_rectangle() || !filter.uses_mipmaps(),
+            "rectangle textures can't use mipmap filters"
+        );
+        unsafe { gl::TexParameteri(self.target(), gl::TEXTURE_MIN_FILTER, filter.into()) }
     }
 
     pub fn set_magnify_filter(&self, filter: MagnifyFilter) {
-        unsafe { gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter.into()) }
+        unsafe { gl::TexParameteri(self.target(), gl::TEXTURE_MAG_FILTER, filter.into()) }
+    }
+
+    /// Sets the range of mip levels that sampling may use. Rectangle textures must use a base
+    /// level of 0.
+    pub fn set_mip_levels(&self, base: GLint, max: GLint) {
+        assert!(
+            base == 0 || !self.is_rectangle(),
+            "rectangle textures have no mip levels"
+        );
+        unsafe {
+            gl::TexParameteri(self.target(), gl::TEXTURE_BASE_LEVEL, base);
+            gl::TexParameteri(self.target(), gl::TEXTURE_MAX_LEVEL, max);
+        }
+    }
+
+    /// Builds every mip level from level 0 on the GPU.
+    pub fn generate_mipmaps(&self) {
+        assert!(
+            !self.is_rectangle(),
+            "rectangle textures have no mip levels"
+        );
+        unsafe { gl::GenerateMipmap(self.target()) }
+    }
+
+    /// Sets the anisotropic filtering level, clamped to what the driver supports. Returns false
+    /// if anisotropic filtering is unavailable.
+    pub fn set_anisotropy(&self, anisotropy: f32) -> bool {
+        match max_anisotropy() {
+            Some(max) => {
+                unsafe {
+                    gl::TexParameterf(
+                        self.target(),
+                        TEXTURE_MAX_ANISOTROPY,
+                        anisotropy.clamp(1.0, max),
+                    )
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Uploads a decoded image as level 0. Grayscale images are stored in the red (and green)
+    /// channels and swizzled so shaders still see luminance in RGB.
+    pub fn write<I>(&self, image: I) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.write_level(0, image)
+    }
+
+    /// Uploads a decoded image as an explicit mip level. Rectangle textures only have level 0.
+    pub fn write_level<I>(&self, level: GLint, image: I) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::Texture2D, TextureTarget::Rectangle]);
+        assert!(
+            level == 0 || !self.is_rectangle(),
+            "rectangle textures have no mip levels"
+        );
+        self.upload(self.target(), level, &DecodedImage::decode(image)?);
+        Ok(())
+    }
+
+    /// Uploads a decoded image as level 0 and fills in the rest of the mip chain.
+    pub fn write_mipmapped<I>(
+        &self,
+        image: I,
+        mipmaps: MipmapGeneration,
+    ) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::Texture2D]);
+        let image = DecodedImage::decode(image)?;
+        self.write_chain(vec![image], mipmaps, |level, images| {
+            self.upload(gl::TEXTURE_2D, level, &images[0]);
+            Ok(())
+        })
+    }
+
+    /// Uploads one face of a cube map.
+    pub fn write_face<I>(&self, face: CubeFace, image: I) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::CubeMap]);
+        self.upload(face.into(), 0, &DecodedImage::decode(image)?);
+        Ok(())
+    }
+
+    /// Uploads all six faces of a cube map, in `CubeFace::ALL` order. The faces must be square
+    /// and equally sized.
+    pub fn write_faces<I>(&self, faces: [I; 6]) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::CubeMap]);
+        self.upload_faces(0, &decode_all(faces)?)
     }
 
-    pub fn write<I>(&self, mut image: I)
+    /// Like `write_faces`, but also fills in the rest of each face's mip chain.
+    pub fn write_faces_mipmapped<I>(
+        &self,
+        faces: [I; 6],
+        mipmaps: MipmapGeneration,
+    ) -> Result<(), TextureError>
     where
         I: ImageDecoder,
     {
-        let (width, height) = image.dimensions().unwrap();
-        let (int_format, format) = match image.colortype().unwrap() {
-            ColorType::RGB(_) => (gl::RGB8, gl::RGB),
-            ColorType::RGBA(_) => (gl::RGBA8, gl::RGBA),
-            _ => unimplemented!(),
+        self.assert_target(&[TextureTarget::CubeMap]);
+        self.write_chain(decode_all(faces)?, mipmaps, |level, faces| {
+            self.upload_faces(level, faces)
+        })
+    }
+
+    /// Uploads a cube map stored as a single image with the faces laid out in a grid.
+    pub fn write_cube_layout<I>(&self, image: I, layout: CubeLayout) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::CubeMap]);
+        let faces = DecodedImage::decode(image)?.split_cube(layout)?;
+        self.upload_faces(0, &faces)
+    }
+
+    /// Like `write_cube_layout`, but also fills in the rest of each face's mip chain.
+    pub fn write_cube_layout_mipmapped<I>(
+        &self,
+        image: I,
+        layout: CubeLayout,
+        mipmaps: MipmapGeneration,
+    ) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::CubeMap]);
+        let faces = DecodedImage::decode(image)?.split_cube(layout)?;
+        self.write_chain(faces, mipmaps, |level, faces| {
+            self.upload_faces(level, faces)
+        })
+    }
+
+    /// Uploads the layers of a 2D array texture, or the slices of a 3D texture, as level 0.
+    /// Every image must have the same size and color type.
+    pub fn write_layers<L>(&self, layers: L) -> Result<(), TextureError>
+    where
+        L: IntoIterator,
+        L::Item: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::Texture2DArray, TextureTarget::Texture3D]);
+        self.upload_layers(0, &decode_all(layers)?)
+    }
+
+    /// Like `write_layers`, but also fills in the rest of the mip chain. Array layers are
+    /// filtered separately, while 3D textures are also filtered across slices.
+    pub fn write_layers_mipmapped<L>(
+        &self,
+        layers: L,
+        mipmaps: MipmapGeneration,
+    ) -> Result<(), TextureError>
+    where
+        L: IntoIterator,
+        L::Item: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::Texture2DArray, TextureTarget::Texture3D]);
+        self.write_chain(decode_all(layers)?, mipmaps, |level, layers| {
+            self.upload_layers(level, layers)
+        })
+    }
+
+    /// Uploads a single image split into `layers` equal columns, such as a row of sprite frames
+    /// or the slices of a color grading LUT, as the layers of an array or 3D texture.
+    pub fn write_strip<I>(&self, image: I, layers: u32) -> Result<(), TextureError>
+    where
+        I: ImageDecoder,
+    {
+        self.assert_target(&[TextureTarget::Texture2DArray, TextureTarget::Texture3D]);
+        let image = DecodedImage::decode(image)?;
+        let width = image.width / layers.max(1);
+        if width == 0 || width * layers != image.width {
+            return Err(TextureError::InvalidLayout {
+                width: image.width,
+                height: image.height,
+            });
+        }
+
+        let layers: Vec<_> = (0..layers)
+            .map(|i| image.crop(i * width, 0, width, image.height, false))
+            .collect();
+        self.upload_layers(0, &layers)
+    }
+
+    fn is_rectangle(&self) -> bool {
+        self.texture.target == TextureTarget::Rectangle
+    }
+
+    fn assert_target(&self, targets: &[TextureTarget]) {
+        assert!(
+            targets.contains(&self.texture.target),
+            "{:?} textures can't be written with this method",
+            self.texture.target
+        );
+    }
+
+    /// Uploads `images` as level 0 with `upload`, then fills in the rest of the mip chain,
+    /// calling `upload` once per level with the downsampled images.
+    fn write_chain<F>(
+        &self,
+        mut images: Vec<DecodedImage>,
+        mipmaps: MipmapGeneration,
+        upload: F,
+    ) -> Result<(), TextureError>
+    where
+        F: Fn(GLint, &[DecodedImage]) -> Result<(), TextureError>,
+    {
+        upload(0, &images)?;
+        let (width, height) = match images.first() {
+            Some(first) => (first.width, first.height),
+            None => return Ok(()),
+        };
+        if mipmaps == MipmapGeneration::Gpu {
+            self.generate_mipmaps();
+            return Ok(());
+        }
+
+        let volume = self.texture.target == TextureTarget::Texture3D;
+        let depth = if volume { images.len() as u32 } else { 1 };
+        let levels = mipmap::level_count(width.max(height), depth) as GLint;
+        for level in 1..levels {
+            images = images.iter().map(|i| i.downsample(mipmaps)).collect();
+            if volume {
+                images = DecodedImage::downsample_depth(&images, mipmaps);
+            }
+            upload(level, &images)?;
+        }
+        self.set_mip_levels(0, levels - 1);
+        Ok(())
+    }
+
+    fn upload_faces(&self, level: GLint, faces: &[DecodedImage]) -> Result<(), TextureError> {
+        check_matching(faces)?;
+        if let Some(face) = faces.iter().find(|f| f.width != f.height) {
+            return Err(TextureError::InvalidLayout {
+                width: face.width,
+                height: face.height,
+            });
+        }
+        for (&face, image) in CubeFace::ALL.iter().zip(faces) {
+            self.upload(face.into(), level, image);
+        }
+        Ok(())
+    }
+
+    fn upload_layers(&self, level: GLint, layers: &[DecodedImage]) -> Result<(), TextureError> {
+        check_matching(layers)?;
+        let first = match layers.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        let data = concat(layers);
+        let data_ptr = match data {
+            Pixels::U8(ref v) => v.as_ptr() as *const GLvoid,
+            Pixels::U16(ref v) => v.as_ptr() as *const GLvoid,
+        };
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage3D(
+                self.target(),
+                level,
+                first.format.internal as i32,
+                first.width as i32,
+                first.height as i32,
+                layers.len() as i32,
+                0,
+                first.format.format,
+                first.format.ty,
+                data_ptr,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexParameteriv(
+                self.target(),
+                gl::TEXTURE_SWIZZLE_RGBA,
+                first.format.swizzle.as_ptr(),
+            );
+        }
+        Ok(())
+    }
+
+    fn upload(&self, target: GLenum, level: GLint, image: &DecodedImage) {
+        let data = match image.pixels {
+            Pixels::U8(ref v) => v.as_ptr() as *const GLvoid,
+            Pixels::U16(ref v) => v.as_ptr() as *const GLvoid,
         };
         unsafe {
-            match image.read_image().unwrap() {
-                DecodingResult::U8(v) => {
-                    gl::TexImage2D(
-                        gl::TEXTURE_2D,
-                        0,
-                        int_format as i32,
-                        width as i32,
-                        height as i32,
-                        0,
-                        format,
-                        gl::UNSIGNED_BYTE,
-                        v.as_ptr() as *const _,
-                    );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                target,
+                level,
+                image.format.internal as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                image.format.format,
+                image.format.ty,
+                data,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexParameteriv(
+                self.target(),
+                gl::TEXTURE_SWIZZLE_RGBA,
+                image.format.swizzle.as_ptr(),
+            );
+        }
+    }
+}
+
+// From GL_EXT_texture_filter_anisotropic, core in GL 4.6
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/// The highest anisotropic filtering level, or `None` if the extension is missing.
+pub fn max_anisotropy() -> Option<f32> {
+    let supported = caps::has_extension(&[
+        "GL_EXT_texture_filter_anisotropic",
+        "GL_ARB_texture_filter_anisotropic",
+    ]);
+    if !supported {
+        return None;
+    }
+
+    let mut max = 0.0;
+    unsafe { gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max) }
+    Some(max)
+}
+
+/// How `ActiveTexture::write_mipmapped` and the other `*_mipmapped` methods build the smaller
+/// levels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MipmapGeneration {
+    /// `glGenerateMipmap`, with whatever filter the driver uses.
+    Gpu,
+    /// A box filter on the CPU, which gives the same result on every driver.
+    Box,
+    /// A Lanczos filter on the CPU. Sharper than `Box`, but slower and prone to slight ringing.
+    Lanczos,
+}
+
+enum Pixels {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+}
+
+/// Tightly packed, native-endian pixels ready for `glTexImage*`.
+struct DecodedImage {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    pixels: Pixels,
+}
+
+impl DecodedImage {
+    fn decode<I: ImageDecoder>(mut image: I) -> Result<Self, TextureError> {
+        let (width, height) = image.dimensions()?;
+        let color = image.colortype()?;
+        let format = PixelFormat::from_color(color)?;
+        let samples = width as usize * height as usize * format.channels;
+        let check_len = |expected: usize, found: usize| {
+            if expected == found {
+                Ok(())
+            } else {
+                Err(TextureError::InvalidLength { expected, found })
+            }
+        };
+        let pixels = match image.read_image()? {
+            DecodingResult::U8(v) if format.ty == gl::UNSIGNED_BYTE => {
+                let expected = match color {
+                    ColorType::Gray(bits) if bits < 8 => {
+                        (width as usize * bits as usize).div_ceil(8) * height as usize
+                    }
+                    _ => samples,
+                };
+                check_len(expected, v.len())?;
+                Pixels::U8(unpack_gray(v, color, width))
+            }
+            // 16-bit samples delivered as big-endian bytes
+            DecodingResult::U8(v) => {
+                check_len(samples * 2, v.len())?;
+                Pixels::U16(
+                    v.chunks(2)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                        .collect(),
+                )
+            }
+            DecodingResult::U16(v) => {
+                check_len(samples * 2, v.len() * 2)?;
+                Pixels::U16(v)
+            }
+        };
+        Ok(DecodedImage {
+            width,
+            height,
+            format,
+            pixels,
+        })
+    }
+
+    /// Cuts the six faces, in `CubeFace::ALL` order, out of an image laid out as `layout`.
+    fn split_cube(&self, layout: CubeLayout) -> Result<Vec<Self>, TextureError> {
+        let (columns, rows) = layout.grid();
+        let size = self.width / columns;
+        if size == 0 || self.width != size * columns || self.height != size * rows {
+            return Err(TextureError::InvalidLayout {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        Ok(CubeFace::ALL
+            .iter()
+            .map(|&face| {
+                let (column, row, flipped) = layout.cell(face);
+                self.crop(column * size, row * size, size, size, flipped)
+            })
+            .collect())
+    }
+
+    /// Copies out a `width` by `height` rectangle, rotated by 180 degrees if `flipped`.
+    fn crop(&self, x: u32, y: u32, width: u32, height: u32, flipped: bool) -> Self {
+        fn crop<T: Copy>(
+            data: &[T],
+            image: &DecodedImage,
+            (x, y): (u32, u32),
+            (width, height): (u32, u32),
+            flipped: bool,
+        ) -> Vec<T> {
+            let channels = image.format.channels;
+            let (width, height) = (width as usize, height as usize);
+            let mut out = Vec::with_capacity(width * height * channels);
+            for row in 0..height {
+                let row = if flipped { height - 1 - row } else { row };
+                let start = ((y as usize + row) * image.width as usize + x as usize) * channels;
+                let pixels = data[start..start + width * channels].chunks(channels);
+                if flipped {
+                    pixels.rev().for_each(|p| out.extend_from_slice(p));
+                } else {
+                    pixels.for_each(|p| out.extend_from_slice(p));
                 }
-                _ => unimplemented!(),
             }
+            out
+        }
+
+        let pixels = match self.pixels {
+            Pixels::U8(ref v) => Pixels::U8(crop(v, self, (x, y), (width, height), flipped)),
+            Pixels::U16(ref v) => Pixels::U16(crop(v, self, (x, y), (width, height), flipped)),
+        };
+        DecodedImage {
+            width,
+            height,
+            format: self.format,
+            pixels,
+        }
+    }
+
+    /// Halves the image with the CPU filter `mipmaps` names.
+    fn downsample(&self, mipmaps: MipmapGeneration) -> Self {
+        let (w, h, channels) = (self.width, self.height, self.format.channels);
+        let pixels = match self.pixels {
+            Pixels::U8(ref v) => Pixels::U8(downsample(v, w, h, channels, mipmaps)),
+            Pixels::U16(ref v) => Pixels::U16(downsample(v, w, h, channels, mipmaps)),
+        };
+        let (width, height) = mipmap::next_size(self.width, self.height);
+        DecodedImage {
+            width,
+            height,
+            format: self.format,
+            pixels,
         }
     }
+
+    /// Halves the number of slices of a 3D texture level.
+    fn downsample_depth(slices: &[Self], mipmaps: MipmapGeneration) -> Vec<Self> {
+        let first = &slices[0];
+        let depth = slices.len() as u32;
+        // A stack of slices is a 1-texel wide image with one channel per sample in a slice
+        let len = first.width as usize * first.height as usize * first.format.channels;
+        let pixels: Vec<_> = match concat(slices) {
+            Pixels::U8(v) => downsample(&v, 1, depth, len, mipmaps)
+                .chunks(len)
+                .map(|s| Pixels::U8(s.to_vec()))
+                .collect(),
+            Pixels::U16(v) => downsample(&v, 1, depth, len, mipmaps)
+                .chunks(len)
+                .map(|s| Pixels::U16(s.to_vec()))
+                .collect(),
+        };
+        pixels
+            .into_iter()
+            .map(|pixels| DecodedImage {
+                width: first.width,
+                height: first.height,
+                format: first.format,
+                pixels,
+            })
+            .collect()
+    }
+}
+
+fn decode_all<L>(images: L) -> Result<Vec<DecodedImage>, TextureError>
+where
+    L: IntoIterator,
+    L::Item: ImageDecoder,
+{
+    images.into_iter().map(DecodedImage::decode).collect()
+}
+
+/// Fails unless every image has the size and format of the first.
+fn check_matching(images: &[DecodedImage]) -> Result<(), TextureError> {
+    match images.first() {
+        Some(first)
+            if images.iter().any(|i| {
+                i.width != first.width
+                    || i.height != first.height
+                    || i.format.internal != first.format.internal
+            }) =>
+        {
+            Err(TextureError::MismatchedLayers)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Joins the pixels of images that passed `check_matching` into one buffer.
+fn concat(images: &[DecodedImage]) -> Pixels {
+    match images[0].pixels {
+        Pixels::U8(_) => Pixels::U8(
+            images
+                .iter()
+                .flat_map(|i| match i.pixels {
+                    Pixels::U8(ref v) => v.iter().cloned(),
+                    Pixels::U16(_) => unreachable!(),
+                })
+                .collect(),
+        ),
+        Pixels::U16(_) => Pixels::U16(
+            images
+                .iter()
+                .flat_map(|i| match i.pixels {
+                    Pixels::U16(ref v) => v.iter().cloned(),
+                    Pixels::U8(_) => unreachable!(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn downsample<T: mipmap::Sample>(
+    data: &[T],
+    width: u32,
+    height: u32,
+    channels: usize,
+    mipmaps: MipmapGeneration,
+) -> Vec<T> {
+    match mipmaps {
+        MipmapGeneration::Lanczos => mipmap::downsample_lanczos(data, width, height, channels),
+        _ => mipmap::downsample_box(data, width, height, channels),
+    }
+}
+
+const SWIZZLE_RGBA: [GLint; 4] = [
+    gl::RED as GLint,
+    gl::GREEN as GLint,
+    gl::BLUE as GLint,
+    gl::ALPHA as GLint,
+];
+const SWIZZLE_GRAY: [GLint; 4] = [
+    gl::RED as GLint,
+    gl::RED as GLint,
+    gl::RED as GLint,
+    gl::ONE as GLint,
+];
+const SWIZZLE_GRAY_ALPHA: [GLint; 4] = [
+    gl::RED as GLint,
+    gl::RED as GLint,
+    gl::RED as GLint,
+    gl::GREEN as GLint,
+];
+
+#[derive(Clone, Copy)]
+struct PixelFormat {
+    internal: GLenum,
+    format: GLenum,
+    ty: GLenum,
+    channels: usize,
+    swizzle: [GLint; 4],
+}
+
+impl PixelFormat {
+    fn from_color(color: ColorType) -> Result<Self, TextureError> {
+        let (internal, format, ty, swizzle) = match color {
+            ColorType::Gray(1) | ColorType::Gray(2) | ColorType::Gray(4) | ColorType::Gray(8) => {
+                (gl::R8, gl::RED, gl::UNSIGNED_BYTE, SWIZZLE_GRAY)
+            }
+            ColorType::Gray(16) => (gl::R16, gl::RED, gl::UNSIGNED_SHORT, SWIZZLE_GRAY),
+            ColorType::GrayA(8) => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, SWIZZLE_GRAY_ALPHA),
+            ColorType::GrayA(16) => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT, SWIZZLE_GRAY_ALPHA),
+            ColorType::RGB(8) => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, SWIZZLE_RGBA),
+            ColorType::RGB(16) => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, SWIZZLE_RGBA),
+            ColorType::RGBA(8) => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, SWIZZLE_RGBA),
+            ColorType::RGBA(16) => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, SWIZZLE_RGBA),
+            // Palette indices are useless without the palette, which decoders don't expose
+            _ => return Err(TextureError::UnsupportedColor(color)),
+        };
+        let channels = match format {
+            gl::RED => 1,
+            gl::RG => 2,
+            gl::RGB => 3,
+            _ => 4,
+        };
+        Ok(PixelFormat {
+            internal,
+            format,
+            ty,
+            channels,
+            swizzle,
+        })
+    }
+}
+
+/// Expands 1, 2 and 4-bit grayscale rows, which start on byte boundaries, to 8 bits per pixel.
+fn unpack_gray(data: Vec<u8>, color: ColorType, width: u32) -> Vec<u8> {
+    let bits = match color {
+        ColorType::Gray(bits) if bits < 8 => bits as usize,
+        _ => return data,
+    };
+    let width = width as usize;
+    let row_len = (width * bits).div_ceil(8);
+    let max = (1 << bits) - 1;
+    let mut out = Vec::with_capacity(data.len() * 8 / bits);
+    for row in data.chunks(row_len) {
+        for x in 0..width {
+            let bit = x * bits;
+            let value = (row[bit / 8] >> (8 - bits - bit % 8)) & max as u8;
+            out.push((value as u32 * 255 / max) as u8);
+        }
+    }
+    out
 }
 
 impl<'a> Drop for ActiveTexture<'a> {
     fn drop(&mut self) {
-        unsafe { gl::BindTexture(gl::TEXTURE_2D, 0) }
+        unsafe { gl::BindTexture(self.target(), 0) }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Decode(ImageError),
+    /// The image's color type has no matching GL format.
+    UnsupportedColor(ColorType),
+    /// The image's dimensions do not fit the requested cube map or strip layout, or a cube map
+    /// face is not square.
+    InvalidLayout {
+        width: u32,
+        height: u32,
+    },
+    /// The layers of an array or 3D texture differ in size or color type.
+    MismatchedLayers,
+    /// The decoder returned a different number of bytes than the image's size and color type
+    /// require.
+    InvalidLength {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl From<ImageError> for TextureError {
+    fn from(error: ImageError) -> Self {
+        TextureError::Decode(error)
+    }
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TextureError::Decode(ref error) => write!(f, "failed to decode image: {}", error),
+            TextureError::UnsupportedColor(color) => {
+                write!(f, "unsupported image color type {:?}", color)
+            }
+            TextureError::InvalidLayout { width, height } => write!(
+                f,
+                "a {}x{} image does not fit the requested layout",
+                width, height
+            ),
+            TextureError::MismatchedLayers => {
+                write!(f, "texture layers differ in size or color type")
+            }
+            TextureError::InvalidLength { expected, found } => write!(
+                f,
+                "decoded image has {} bytes of pixel data, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            TextureError::Decode(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureTarget {
+    Texture2D,
+    /// Layers of equally sized 2D images, indexed by the third texture coordinate.
+    Texture2DArray,
+    Texture3D,
+    CubeMap,
+    /// A single-level 2D texture addressed in texels rather than normalized coordinates.
+    Rectangle,
+}
+
+impl From<TextureTarget> for GLenum {
+    fn from(target: TextureTarget) -> Self {
+        match target {
+            TextureTarget::Texture2D => gl::TEXTURE_2D,
+            TextureTarget::Texture2DArray => gl::TEXTURE_2D_ARRAY,
+            TextureTarget::Texture3D => gl::TEXTURE_3D,
+            TextureTarget::CubeMap => gl::TEXTURE_CUBE_MAP,
+            TextureTarget::Rectangle => gl::TEXTURE_RECTANGLE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+}
+
+impl From<CubeFace> for GLenum {
+    fn from(face: CubeFace) -> Self {
+        match face {
+            CubeFace::PositiveX => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            CubeFace::NegativeX => gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            CubeFace::PositiveY => gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+            CubeFace::NegativeY => gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+            CubeFace::PositiveZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+            CubeFace::NegativeZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+        }
+    }
+}
+
+/// How the six faces of a cube map are arranged in a single image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubeLayout {
+    /// A 4x3 grid: `+Y` above and `-Y` below `+Z`, with `-X +Z +X -Z` across the middle.
+    HorizontalCross,
+    /// A 3x4 grid: `+Y`, `+Z`, `-Y` and `-Z` down the middle column, with `-X` and `+X` beside
+    /// `+Z`. `-Z` is stored upside down.
+    VerticalCross,
+    /// A 6x1 row in `CubeFace::ALL` order.
+    HorizontalStrip,
+    /// A 1x6 column in `CubeFace::ALL` order.
+    VerticalStrip,
+}
+
+impl CubeLayout {
+    /// The grid size in faces, as (columns, rows).
+    fn grid(self) -> (u32, u32) {
+        match self {
+            CubeLayout::HorizontalCross => (4, 3),
+            CubeLayout::VerticalCross => (3, 4),
+            CubeLayout::HorizontalStrip => (6, 1),
+            CubeLayout::VerticalStrip => (1, 6),
+        }
+    }
+
+    /// The grid cell holding a face, and whether the face is stored rotated by 180 degrees.
+    fn cell(self, face: CubeFace) -> (u32, u32, bool) {
+        let index = CubeFace::ALL.iter().position(|&f| f == face).unwrap() as u32;
+        match (self, face) {
+            (CubeLayout::HorizontalStrip, _) => (index, 0, false),
+            (CubeLayout::VerticalStrip, _) => (0, index, false),
+            (_, CubeFace::PositiveX) => (2, 1, false),
+            (_, CubeFace::NegativeX) => (0, 1, false),
+            (_, CubeFace::PositiveY) => (1, 0, false),
+            (_, CubeFace::NegativeY) => (1, 2, false),
+            (_, CubeFace::PositiveZ) => (1, 1, false),
+            (CubeLayout::HorizontalCross, CubeFace::NegativeZ) => (3, 1, false),
+            (_, CubeFace::NegativeZ) => (1, 3, true),
+        }
     }
 }
 
@@ -133,9 +1004,16 @@ pub enum MinifyFilter {
     LinearMipmapLinear,
 }
 
-impl Into<GLint> for MinifyFilter {
-    fn into(self) -> GLint {
-        match self {
+impl MinifyFilter {
+    /// Whether the filter samples from more than level 0.
+    pub fn uses_mipmaps(&self) -> bool {
+        !matches!(*self, MinifyFilter::Nearest | MinifyFilter::Linear)
+    }
+}
+
+impl From<MinifyFilter> for GLint {
+    fn from(filter: MinifyFilter) -> Self {
+        match filter {
             MinifyFilter::Nearest => gl::NEAREST as i32,
             MinifyFilter::Linear => gl::LINEAR as i32,
             MinifyFilter::NearestMipmapNearest => gl::NEAREST_MIPMAP_NEAREST as i32,
@@ -151,15 +1029,37 @@ pub enum MagnifyFilter {
     Linear,
 }
 
-impl Into<GLint> for MagnifyFilter {
-    fn into(self) -> GLint {
-        match self {
+impl From<MagnifyFilter> for GLint {
+    fn from(filter: MagnifyFilter) -> Self {
+        match filter {
             MagnifyFilter::Nearest => gl::NEAREST as i32,
             MagnifyFilter::Linear => gl::LINEAR as i32,
         }
     }
 }
 
+/// The wrap functions for each texture coordinate axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrap {
+    pub s: WrapFunction,
+    pub t: WrapFunction,
+    /// Left unchanged when `None`.
+    pub r: Option<WrapFunction>,
+}
+
+impl From<(WrapFunction, WrapFunction)> for Wrap {
+    fn from((s, t): (WrapFunction, WrapFunction)) -> Self {
+        Wrap { s, t, r: None }
+    }
+}
+
+impl From<(WrapFunction, WrapFunction, WrapFunction)> for Wrap {
+    fn from((s, t, r): (WrapFunction, WrapFunction, WrapFunction)) -> Self {
+        Wrap { s, t, r: Some(r) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WrapFunction {
     Repeat,
     Mirror,
@@ -167,9 +1067,9 @@ pub enum WrapFunction {
     MirrorClamp,
 }
 
-impl Into<GLint> for WrapFunction {
-    fn into(self) -> GLint {
-        match self {
+impl From<WrapFunction> for GLint {
+    fn from(wrap: WrapFunction) -> Self {
+        match wrap {
             WrapFunction::Repeat => gl::REPEAT as i32,
             WrapFunction::Mirror => gl::MIRRORED_REPEAT as i32,
             WrapFunction::Clamp => gl::CLAMP_TO_EDGE as i32,
@@ -177,3 +1077,152 @@ impl Into<GLint> for WrapFunction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpacks_low_bit_gray() {
+        assert_eq!(
+            unpack_gray(vec![0b1010_0000, 0b0100_0000], ColorType::Gray(1), 3),
+            [255, 0, 255, 0, 255, 0]
+        );
+        assert_eq!(
+            unpack_gray(vec![0b1101_0000], ColorType::Gray(2), 2),
+            [255, 85]
+        );
+        assert_eq!(
+            unpack_gray(vec![0xf0, 0x80], ColorType::Gray(4), 3),
+            [255, 0, 136]
+        );
+    }
+
+    #[test]
+    fn leaves_8_bit_gray_alone() {
+        let data = vec![0, 64, 128, 255];
+        assert_eq!(unpack_gray(data.clone(), ColorType::Gray(8), 4), data);
+        assert_eq!(unpack_gray(data.clone(), ColorType::GrayA(8), 2), data);
+    }
+
+    #[test]
+    fn gray_is_swizzled_to_luminance() {
+        let format = PixelFormat::from_color(ColorType::Gray(4)).unwrap();
+        assert_eq!((format.internal, format.format), (gl::R8, gl::RED));
+        assert_eq!(format.channels, 1);
+        assert_eq!(format.swizzle, SWIZZLE_GRAY);
+
+        let format = PixelFormat::from_color(ColorType::GrayA(16)).unwrap();
+        assert_eq!((format.internal, format.format), (gl::RG16, gl::RG));
+        assert_eq!(format.ty, gl::UNSIGNED_SHORT);
+        assert_eq!(format.channels, 2);
+        assert_eq!(
+            format.swizzle,
+            [
+                gl::RED as GLint,
+                gl::RED as GLint,
+                gl::RED as GLint,
+                gl::GREEN as GLint
+            ]
+        );
+    }
+
+    #[test]
+    fn color_keeps_its_channels() {
+        let format = PixelFormat::from_color(ColorType::RGB(8)).unwrap();
+        assert_eq!((format.internal, format.format), (gl::RGB8, gl::RGB));
+        assert_eq!(format.channels, 3);
+        assert_eq!(format.swizzle, SWIZZLE_RGBA);
+
+        let format = PixelFormat::from_color(ColorType::RGBA(16)).unwrap();
+        assert_eq!(
+            (format.internal, format.ty),
+            (gl::RGBA16, gl::UNSIGNED_SHORT)
+        );
+        assert_eq!(format.channels, 4);
+    }
+
+    #[test]
+    fn palettes_are_unsupported() {
+        assert!(matches!(
+            PixelFormat::from_color(ColorType::Palette(8)),
+            Err(TextureError::UnsupportedColor(ColorType::Palette(8)))
+        ));
+    }
+
+    /// A gray image of `columns` by `rows` cells of 2x2 texels. Each cell's texels are
+    /// `16 * cell + 0..4` in reading order, with cells also numbered in reading order.
+    fn grid(columns: u32, rows: u32) -> DecodedImage {
+        let (width, height) = (columns * 2, rows * 2);
+        let pixels = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| ((y / 2 * columns + x / 2) * 16 + y % 2 * 2 + x % 2) as u8)
+            })
+            .collect();
+        DecodedImage {
+            width,
+            height,
+            format: PixelFormat::from_color(ColorType::Gray(8)).unwrap(),
+            pixels: Pixels::U8(pixels),
+        }
+    }
+
+    /// The texels of each face, in `CubeFace::ALL` order.
+    fn faces(image: &DecodedImage, layout: CubeLayout) -> Vec<Vec<u8>> {
+        image
+            .split_cube(layout)
+            .unwrap()
+            .into_iter()
+            .map(|face| {
+                assert_eq!((face.width, face.height), (2, 2));
+                match face.pixels {
+                    Pixels::U8(v) => v,
+                    Pixels::U16(_) => unreachable!(),
+                }
+            })
+            .collect()
+    }
+
+    fn cell(index: u8) -> Vec<u8> {
+        (0..4).map(|i| index * 16 + i).collect()
+    }
+
+    #[test]
+    fn splits_horizontal_cross() {
+        // +Y above and -Y below +Z, with -X +Z +X -Z across the middle
+        let faces = faces(&grid(4, 3), CubeLayout::HorizontalCross);
+        assert_eq!(
+            faces,
+            [cell(6), cell(4), cell(1), cell(9), cell(5), cell(7)]
+        );
+    }
+
+    #[test]
+    fn splits_vertical_cross_with_flipped_negative_z() {
+        let faces = faces(&grid(3, 4), CubeLayout::VerticalCross);
+        assert_eq!(&faces[..5], [cell(5), cell(3), cell(1), cell(7), cell(4)]);
+        assert_eq!(faces[5], [163, 162, 161, 160]);
+    }
+
+    #[test]
+    fn splits_strips_in_face_order() {
+        let expected: Vec<_> = (0..6).map(cell).collect();
+        assert_eq!(faces(&grid(6, 1), CubeLayout::HorizontalStrip), expected);
+        assert_eq!(faces(&grid(1, 6), CubeLayout::VerticalStrip), expected);
+    }
+
+    #[test]
+    fn rejects_images_that_do_not_fit_the_layout() {
+        assert!(matches!(
+            grid(4, 2).split_cube(CubeLayout::HorizontalCross),
+            Err(TextureError::InvalidLayout {
+                width: 8,
+                height: 4
+            })
+        ));
+        assert!(matches!(
+            grid(6, 1).split_cube(CubeLayout::VerticalStrip),
+            Err(TextureError::InvalidLayout { .. })
+        ));
+    }
+}
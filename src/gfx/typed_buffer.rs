@@ -0,0 +1,171 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::mem;
+use std::slice;
+
+use cgmath::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use gfx::buffer::{ActiveBuffer, Buffer, BufferType, BufferUsage};
+
+use gl;
+use gl::types::*;
+
+/// Plain old data that can be copied to the GPU byte for byte.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` (or primitive) and contain no padding or pointers.
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl<T: Pod> Pod for Vector2<T> {}
+unsafe impl<T: Pod> Pod for Vector3<T> {}
+unsafe impl<T: Pod> Pod for Vector4<T> {}
+unsafe impl<T: Pod> Pod for Matrix2<T> {}
+unsafe impl<T: Pod> Pod for Matrix3<T> {}
+unsafe impl<T: Pod> Pod for Matrix4<T> {}
+
+pub fn as_bytes<T: Pod>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+/// The type of the elements in an index buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexType {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexType {
+    /// The size of one index in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+}
+
+impl From<IndexType> for GLenum {
+    fn from(ty: IndexType) -> Self {
+        match ty {
+            IndexType::U8 => gl::UNSIGNED_BYTE,
+            IndexType::U16 => gl::UNSIGNED_SHORT,
+            IndexType::U32 => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// An integer type usable in an index buffer.
+pub trait Index: Pod {
+    const TYPE: IndexType;
+}
+
+impl Index for u8 {
+    const TYPE: IndexType = IndexType::U8;
+}
+
+impl Index for u16 {
+    const TYPE: IndexType = IndexType::U16;
+}
+
+impl Index for u32 {
+    const TYPE: IndexType = IndexType::U32;
+}
+
+/// A `Buffer` holding elements of a single type.
+#[derive(Debug)]
+pub struct TypedBuffer<T: Pod> {
+    buffer: Buffer,
+    len: Cell<usize>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> TypedBuffer<T> {
+    pub fn new(ty: BufferType) -> Self {
+        Self::with_usage(ty, BufferUsage::Static)
+    }
+
+    pub fn with_usage(ty: BufferType, usage: BufferUsage) -> Self {
+        TypedBuffer {
+            buffer: Buffer::with_usage(ty, usage),
+            len: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn into_inner(self) -> Buffer {
+        self.buffer
+    }
+
+    pub fn bind(&self) -> ActiveTypedBuffer<'_, T> {
+        ActiveTypedBuffer {
+            active: self.buffer.bind(),
+            buffer: self,
+        }
+    }
+}
+
+pub struct ActiveTypedBuffer<'a, T: Pod + 'a> {
+    active: ActiveBuffer<'a>,
+    buffer: &'a TypedBuffer<T>,
+}
+
+impl<'a, T: Pod> ActiveTypedBuffer<'a, T> {
+    /// Replaces the buffer's storage with `data`.
+    pub fn buffer(&self, data: &[T]) {
+        self.active.buffer(as_bytes(data));
+        self.buffer.len.set(data.len());
+    }
+
+    /// Copies the elements back from the GPU.
+    pub fn read_back(&self) -> Vec<T> {
+        let len = self.buffer.len();
+        let mut data = Vec::with_capacity(len);
+        unsafe {
+            gl::GetBufferSubData(
+                self.buffer.buffer.ty().into(),
+                0,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                data.as_mut_ptr() as *mut _,
+            );
+            data.set_len(len);
+        }
+        data
+    }
+
+    /// Overwrites elements starting at element `offset`.
+    pub fn sub_data(&self, offset: usize, data: &[T]) {
+        let byte_offset = offset.checked_mul(mem::size_of::<T>()).unwrap_or_else(|| {
+            panic!(
+                "sub_data of {} elements at {} out of bounds for buffer of {} elements",
+                data.len(),
+                offset,
+                self.buffer.len()
+            )
+        });
+        self.active.sub_data(byte_offset, as_bytes(data));
+    }
+}
@@ -0,0 +1,259 @@
+use cgmath::{Matrix, Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use gfx::reflection::GlslType;
+use gl;
+use gl::types::*;
+
+/// A value for any GLSL scalar, vector or float matrix uniform, or an array of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue<'a> {
+    F1(f32),
+    F2(Vector2<f32>),
+    F3(Vector3<f32>),
+    F4(Vector4<f32>),
+    I1(i32),
+    I2(Vector2<i32>),
+    I3(Vector3<i32>),
+    I4(Vector4<i32>),
+    U1(u32),
+    U2(Vector2<u32>),
+    U3(Vector3<u32>),
+    U4(Vector4<u32>),
+    Bool(bool),
+    Matrix2(Matrix2<f32>),
+    Matrix3(Matrix3<f32>),
+    Matrix4(Matrix4<f32>),
+
+    F1Array(&'a [f32]),
+    F2Array(&'a [Vector2<f32>]),
+    F3Array(&'a [Vector3<f32>]),
+    F4Array(&'a [Vector4<f32>]),
+    I1Array(&'a [i32]),
+    I2Array(&'a [Vector2<i32>]),
+    I3Array(&'a [Vector3<i32>]),
+    I4Array(&'a [Vector4<i32>]),
+    U1Array(&'a [u32]),
+    U2Array(&'a [Vector2<u32>]),
+    U3Array(&'a [Vector3<u32>]),
+    U4Array(&'a [Vector4<u32>]),
+    BoolArray(&'a [bool]),
+    Matrix2Array(&'a [Matrix2<f32>]),
+    Matrix3Array(&'a [Matrix3<f32>]),
+    Matrix4Array(&'a [Matrix4<f32>]),
+}
+
+impl<'a> UniformValue<'a> {
+    /// The GLSL type of a single element.
+    pub fn glsl_type(&self) -> GlslType {
+        match *self {
+            UniformValue::F1(_) | UniformValue::F1Array(_) => GlslType::Float,
+            UniformValue::F2(_) | UniformValue::F2Array(_) => GlslType::Vec2,
+            UniformValue::F3(_) | UniformValue::F3Array(_) => GlslType::Vec3,
+            UniformValue::F4(_) | UniformValue::F4Array(_) => GlslType::Vec4,
+            UniformValue::I1(_) | UniformValue::I1Array(_) => GlslType::Int,
+            UniformValue::I2(_) | UniformValue::I2Array(_) => GlslType::IVec2,
+            UniformValue::I3(_) | UniformValue::I3Array(_) => GlslType::IVec3,
+            UniformValue::I4(_) | UniformValue::I4Array(_) => GlslType::IVec4,
+            UniformValue::U1(_) | UniformValue::U1Array(_) => GlslType::UInt,
+            UniformValue::U2(_) | UniformValue::U2Array(_) => GlslType::UVec2,
+            UniformValue::U3(_) | UniformValue::U3Array(_) => GlslType::UVec3,
+            UniformValue::U4(_) | UniformValue::U4Array(_) => GlslType::UVec4,
+            UniformValue::Bool(_) | UniformValue::BoolArray(_) => GlslType::Bool,
+            UniformValue::Matrix2(_) | UniformValue::Matrix2Array(_) => GlslType::Mat2,
+            UniformValue::Matrix3(_) | UniformValue::Matrix3Array(_) => GlslType::Mat3,
+            UniformValue::Matrix4(_) | UniformValue::Matrix4Array(_) => GlslType::Mat4,
+        }
+    }
+
+    /// Whether the value is an empty array.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of elements, 1 for non-arrays.
+    pub fn len(&self) -> usize {
+        match *self {
+            UniformValue::F1Array(v) => v.len(),
+            UniformValue::F2Array(v) => v.len(),
+            UniformValue::F3Array(v) => v.len(),
+            UniformValue::F4Array(v) => v.len(),
+            UniformValue::I1Array(v) => v.len(),
+            UniformValue::I2Array(v) => v.len(),
+            UniformValue::I3Array(v) => v.len(),
+            UniformValue::I4Array(v) => v.len(),
+            UniformValue::U1Array(v) => v.len(),
+            UniformValue::U2Array(v) => v.len(),
+            UniformValue::U3Array(v) => v.len(),
+            UniformValue::U4Array(v) => v.len(),
+            UniformValue::BoolArray(v) => v.len(),
+            UniformValue::Matrix2Array(v) => v.len(),
+            UniformValue::Matrix3Array(v) => v.len(),
+            UniformValue::Matrix4Array(v) => v.len(),
+            _ => 1,
+        }
+    }
+
+    /// Whether a uniform declared as `declared` can be set from this value.
+    pub fn matches(&self, declared: GlslType) -> bool {
+        let ty = self.glsl_type();
+        ty == declared
+            || match declared {
+                GlslType::Bool => ty == GlslType::Int,
+                GlslType::BVec2 => ty == GlslType::IVec2,
+                GlslType::BVec3 => ty == GlslType::IVec3,
+                GlslType::BVec4 => ty == GlslType::IVec4,
+                _ => (declared.is_sampler() || declared.is_image()) && ty == GlslType::Int,
+            }
+    }
+
+    /// A copy of a non-array value that outlives the borrow, for comparing against a previous
+    /// upload. Arrays return `None`.
+    pub fn to_static(&self) -> Option<UniformValue<'static>> {
+        match *self {
+            UniformValue::F1(v) => Some(UniformValue::F1(v)),
+            UniformValue::F2(v) => Some(UniformValue::F2(v)),
+            UniformValue::F3(v) => Some(UniformValue::F3(v)),
+            UniformValue::F4(v) => Some(UniformValue::F4(v)),
+            UniformValue::I1(v) => Some(UniformValue::I1(v)),
+            UniformValue::I2(v) => Some(UniformValue::I2(v)),
+            UniformValue::I3(v) => Some(UniformValue::I3(v)),
+            UniformValue::I4(v) => Some(UniformValue::I4(v)),
+            UniformValue::U1(v) => Some(UniformValue::U1(v)),
+            UniformValue::U2(v) => Some(UniformValue::U2(v)),
+            UniformValue::U3(v) => Some(UniformValue::U3(v)),
+            UniformValue::U4(v) => Some(UniformValue::U4(v)),
+            UniformValue::Bool(v) => Some(UniformValue::Bool(v)),
+            UniformValue::Matrix2(v) => Some(UniformValue::Matrix2(v)),
+            UniformValue::Matrix3(v) => Some(UniformValue::Matrix3(v)),
+            UniformValue::Matrix4(v) => Some(UniformValue::Matrix4(v)),
+            _ => None,
+        }
+    }
+
+    /// Uploads the value to `location` of the program in use.
+    pub fn upload(&self, location: GLint) {
+        let count = self.len() as GLsizei;
+        unsafe {
+            match *self {
+                UniformValue::F1(v) => gl::Uniform1f(location, v),
+                UniformValue::F2(v) => gl::Uniform2f(location, v.x, v.y),
+                UniformValue::F3(v) => gl::Uniform3f(location, v.x, v.y, v.z),
+                UniformValue::F4(v) => gl::Uniform4f(location, v.x, v.y, v.z, v.w),
+                UniformValue::I1(v) => gl::Uniform1i(location, v),
+                UniformValue::I2(v) => gl::Uniform2i(location, v.x, v.y),
+                UniformValue::I3(v) => gl::Uniform3i(location, v.x, v.y, v.z),
+                UniformValue::I4(v) => gl::Uniform4i(location, v.x, v.y, v.z, v.w),
+                UniformValue::U1(v) => gl::Uniform1ui(location, v),
+                UniformValue::U2(v) => gl::Uniform2ui(location, v.x, v.y),
+                UniformValue::U3(v) => gl::Uniform3ui(location, v.x, v.y, v.z),
+                UniformValue::U4(v) => gl::Uniform4ui(location, v.x, v.y, v.z, v.w),
+                UniformValue::Bool(v) => gl::Uniform1i(location, v as GLint),
+                UniformValue::Matrix2(m) => {
+                    gl::UniformMatrix2fv(location, 1, gl::FALSE, m.as_ptr())
+                }
+                UniformValue::Matrix3(m) => {
+                    gl::UniformMatrix3fv(location, 1, gl::FALSE, m.as_ptr())
+                }
+                UniformValue::Matrix4(m) => {
+                    gl::UniformMatrix4fv(location, 1, gl::FALSE, m.as_ptr())
+                }
+
+                UniformValue::F1Array(v) => gl::Uniform1fv(location, count, v.as_ptr()),
+                UniformValue::F2Array(v) => gl::Uniform2fv(location, count, v.as_ptr() as *const _),
+                UniformValue::F3Array(v) => gl::Uniform3fv(location, count, v.as_ptr() as *const _),
+                UniformValue::F4Array(v) => gl::Uniform4fv(location, count, v.as_ptr() as *const _),
+                UniformValue::I1Array(v) => gl::Uniform1iv(location, count, v.as_ptr()),
+                UniformValue::I2Array(v) => gl::Uniform2iv(location, count, v.as_ptr() as *const _),
+                UniformValue::I3Array(v) => gl::Uniform3iv(location, count, v.as_ptr() as *const _),
+                UniformValue::I4Array(v) => gl::Uniform4iv(location, count, v.as_ptr() as *const _),
+                UniformValue::U1Array(v) => gl::Uniform1uiv(location, count, v.as_ptr()),
+                UniformValue::U2Array(v) => {
+                    gl::Uniform2uiv(location, count, v.as_ptr() as *const _)
+                }
+                UniformValue::U3Array(v) => {
+                    gl::Uniform3uiv(location, count, v.as_ptr() as *const _)
+                }
+                UniformValue::U4Array(v) => {
+                    gl::Uniform4uiv(location, count, v.as_ptr() as *const _)
+                }
+                UniformValue::BoolArray(v) => {
+                    let v: Vec<GLint> = v.iter().map(|&b| b as GLint).collect();
+                    gl::Uniform1iv(location, count, v.as_ptr())
+                }
+                UniformValue::Matrix2Array(v) => {
+                    gl::UniformMatrix2fv(location, count, gl::FALSE, v.as_ptr() as *const _)
+                }
+                UniformValue::Matrix3Array(v) => {
+                    gl::UniformMatrix3fv(location, count, gl::FALSE, v.as_ptr() as *const _)
+                }
+                UniformValue::Matrix4Array(v) => {
+                    gl::UniformMatrix4fv(location, count, gl::FALSE, v.as_ptr() as *const _)
+                }
+            }
+        }
+    }
+}
+
+macro_rules! uniform_value_from {
+    ($($ty:ty => $variant:ident,)*) => {
+        $(
+            impl<'a> From<$ty> for UniformValue<'a> {
+                fn from(v: $ty) -> Self {
+                    UniformValue::$variant(v)
+                }
+            }
+        )*
+    }
+}
+
+uniform_value_from! {
+    f32 => F1,
+    Vector2<f32> => F2,
+    Vector3<f32> => F3,
+    Vector4<f32> => F4,
+    i32 => I1,
+    Vector2<i32> => I2,
+    Vector3<i32> => I3,
+    Vector4<i32> => I4,
+    u32 => U1,
+    Vector2<u32> => U2,
+    Vector3<u32> => U3,
+    Vector4<u32> => U4,
+    bool => Bool,
+    Matrix2<f32> => Matrix2,
+    Matrix3<f32> => Matrix3,
+    Matrix4<f32> => Matrix4,
+
+    &'a [f32] => F1Array,
+    &'a [Vector2<f32>] => F2Array,
+    &'a [Vector3<f32>] => F3Array,
+    &'a [Vector4<f32>] => F4Array,
+    &'a [i32] => I1Array,
+    &'a [Vector2<i32>] => I2Array,
+    &'a [Vector3<i32>] => I3Array,
+    &'a [Vector4<i32>] => I4Array,
+    &'a [u32] => U1Array,
+    &'a [Vector2<u32>] => U2Array,
+    &'a [Vector3<u32>] => U3Array,
+    &'a [Vector4<u32>] => U4Array,
+    &'a [bool] => BoolArray,
+    &'a [Matrix2<f32>] => Matrix2Array,
+    &'a [Matrix3<f32>] => Matrix3Array,
+    &'a [Matrix4<f32>] => Matrix4Array,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samplers_and_images_accept_unit_indices() {
+        let unit = UniformValue::from(0i32);
+        assert!(unit.matches(GlslType::Sampler2D));
+        assert!(unit.matches(GlslType::Other(gl::INT_SAMPLER_2D)));
+        assert!(unit.matches(GlslType::Other(gl::SAMPLER_CUBE_SHADOW)));
+        assert!(unit.matches(GlslType::Other(gl::IMAGE_2D)));
+        assert!(unit.matches(GlslType::Other(gl::UNSIGNED_INT_IMAGE_3D)));
+        assert!(!UniformValue::from(0.0f32).matches(GlslType::Sampler2D));
+        assert!(!unit.matches(GlslType::Other(gl::UNSIGNED_INT_ATOMIC_COUNTER)));
+    }
+}
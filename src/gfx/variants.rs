@@ -0,0 +1,76 @@
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+use gfx::preprocessor::Preprocessor;
+use gfx::program_cache::ProgramCache;
+use gfx::shader::{Program, ShaderError, ShaderStage};
+
+/// Lazily builds permutations of one set of shader files, one per combination of features.
+///
+/// Every enabled feature is passed to the shaders as `#define <FEATURE> 1`.
+pub struct ShaderVariants {
+    preprocessor: Preprocessor,
+    sources: Vec<(ShaderStage, String)>,
+    cache: Option<ProgramCache>,
+    programs: HashMap<BTreeSet<String>, Rc<Program>>,
+}
+
+impl ShaderVariants {
+    /// Uses files relative to the preprocessor root.
+    pub fn new(preprocessor: Preprocessor, sources: &[(ShaderStage, &str)]) -> Self {
+        ShaderVariants {
+            preprocessor,
+            sources: sources
+                .iter()
+                .map(|&(stage, name)| (stage, name.to_owned()))
+                .collect(),
+            cache: None,
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Stores built permutations in a program binary cache.
+    pub fn with_cache(mut self, cache: ProgramCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the program for a set of features, building it on first use.
+    pub fn get(&mut self, features: &[&str]) -> Result<Rc<Program>, ShaderError> {
+        let key: BTreeSet<String> = features.iter().map(|&f| f.to_owned()).collect();
+        if let Some(program) = self.programs.get(&key) {
+            return Ok(program.clone());
+        }
+
+        let program = Rc::new(self.build(&key)?);
+        self.programs.insert(key, program.clone());
+        Ok(program)
+    }
+
+    /// Drops every built permutation, e.g. after the shader files changed.
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+
+    fn build(&self, features: &BTreeSet<String>) -> Result<Program, ShaderError> {
+        let mut preprocessor = self.preprocessor.clone();
+        for feature in features {
+            preprocessor.define(feature.as_str(), "1");
+        }
+
+        let processed = self
+            .sources
+            .iter()
+            .map(|&(stage, ref name)| Ok((stage, preprocessor.process_file(name)?)))
+            .collect::<Result<Vec<_>, ShaderError>>()?;
+        let sources: Vec<_> = processed
+            .iter()
+            .map(|&(stage, ref source)| source.shader_source(stage))
+            .collect();
+
+        match self.cache {
+            Some(ref cache) => cache.load_or_build(&sources),
+            None => Program::from_sources(&sources),
+        }
+    }
+}
@@ -1,32 +1,151 @@
 use std::mem;
 
 use gfx::shader::ActiveProgram;
-use gfx::buffer::Buffer;
+use gfx::buffer::{Buffer, BufferType};
+use gfx::typed_buffer::{Index, IndexType, TypedBuffer};
 
 use gl;
 use gl::types::*;
 
+#[derive(Debug, Clone, Copy, Default)]
 pub struct VertexAttrib {
     pub location: GLuint,
     pub size: GLint,
     pub stride: GLsizei,
     pub start: usize,
+    /// Advance the attribute once every `divisor` instances instead of once per vertex.
+    pub divisor: GLuint,
+    /// The type of each component in the buffer.
+    pub ty: AttribType,
+    /// Map integer components to `[0, 1]` or `[-1, 1]` when converting them to floats.
+    pub normalized: bool,
+    /// Pass integer components through unconverted, for `int`/`uint` shader inputs.
+    pub integer: bool,
+}
+
+impl VertexAttrib {
+    /// The distance in bytes between consecutive vertices. A `stride` of 0 means the attribute
+    /// is tightly packed, so this is the size of one attribute.
+    pub fn effective_stride(&self) -> usize {
+        if self.stride == 0 {
+            self.size as usize * self.ty.size()
+        } else {
+            self.stride as usize
+        }
+    }
+}
+
+/// The type of a vertex attribute's components in its buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttribType {
+    #[default]
+    F32,
+    F16,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+}
+
+impl AttribType {
+    /// The size of one component in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            AttribType::I8 | AttribType::U8 => 1,
+            AttribType::F16 | AttribType::I16 | AttribType::U16 => 2,
+            AttribType::F32 | AttribType::I32 | AttribType::U32 => 4,
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, AttribType::F32 | AttribType::F16)
+    }
+}
+
+impl From<AttribType> for GLenum {
+    fn from(ty: AttribType) -> Self {
+        match ty {
+            AttribType::F32 => gl::FLOAT,
+            AttribType::F16 => gl::HALF_FLOAT,
+            AttribType::I8 => gl::BYTE,
+            AttribType::U8 => gl::UNSIGNED_BYTE,
+            AttribType::I16 => gl::SHORT,
+            AttribType::U16 => gl::UNSIGNED_SHORT,
+            AttribType::I32 => gl::INT,
+            AttribType::U32 => gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// A run of elements within a vertex array, such as one submesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawRange {
+    /// The first element: an index into the index buffer, or a vertex when not indexed.
+    pub first: usize,
+    pub count: usize,
+    /// Added to every vertex index, so submeshes can keep indices relative to their own vertices.
+    pub base_vertex: GLint,
+}
+
+impl DrawRange {
+    pub fn new(first: usize, count: usize) -> Self {
+        DrawRange {
+            first,
+            count,
+            base_vertex: 0,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct VertexArray {
     id: GLuint,
     vbo: Buffer,
-    ibo: Option<Buffer>,
+    ibo: Option<(Buffer, IndexType)>,
+    instances: Option<Buffer>,
+    stride: usize,
 }
 
 impl VertexArray {
-    pub fn new(vbo: Buffer, ibo: Option<Buffer>, attribs: &[VertexAttrib]) -> Self {
+    pub fn new(vbo: Buffer, attribs: &[VertexAttrib]) -> Self {
+        Self::build(vbo, None, attribs)
+    }
+
+    /// Creates a vertex array drawn through an index buffer, whose element type is used for
+    /// `glDrawElements`.
+    pub fn indexed<I: Index>(vbo: Buffer, ibo: TypedBuffer<I>, attribs: &[VertexAttrib]) -> Self {
+        Self::build(vbo, Some((ibo.into_inner(), I::TYPE)), attribs)
+    }
+
+    /// Creates an indexed vertex array, storing `indices` as `u16` if they fit and `u32`
+    /// otherwise. `u8` indices are never used, as many GPUs handle them poorly.
+    pub fn with_indices(vbo: Buffer, indices: &[u32], attribs: &[VertexAttrib]) -> Self {
+        if indices.iter().all(|&i| i <= u32::from(u16::MAX)) {
+            let ibo = TypedBuffer::new(BufferType::Index);
+            ibo.bind()
+                .buffer(&indices.iter().map(|&i| i as u16).collect::<Vec<_>>());
+            Self::indexed(vbo, ibo, attribs)
+        } else {
+            let ibo = TypedBuffer::new(BufferType::Index);
+            ibo.bind().buffer(indices);
+            Self::indexed(vbo, ibo, attribs)
+        }
+    }
+
+    fn build(vbo: Buffer, ibo: Option<(Buffer, IndexType)>, attribs: &[VertexAttrib]) -> Self {
         let vao = unsafe {
             let mut id = 0;
             gl::GenVertexArrays(1, &mut id);
 
-            VertexArray { id, vbo, ibo }
+            VertexArray {
+                id,
+                vbo,
+                ibo,
+                instances: None,
+                stride: attribs.first().map_or(0, VertexAttrib::effective_stride),
+            }
         };
 
         {
@@ -34,18 +153,61 @@ impl VertexArray {
 
             // Don't unbind in the vao setup
             mem::forget(vao.vbo.bind());
-            if let Some(ref ibo) = vao.ibo {
+            if let Some((ref ibo, _)) = vao.ibo {
                 mem::forget(ibo.bind());
             }
             for a in attribs {
-                active.vertex_attrib_pointer(a.location, a.size, a.stride, a.start);
+                active.vertex_attrib_pointer(a);
             }
         }
 
         vao
     }
 
-    pub fn bind(&self) -> ActiveVAO {
+    /// Attaches a buffer of per-instance data. Every attribute in `attribs` needs a non-zero
+    /// `divisor`.
+    pub fn with_instances(mut self, instances: Buffer, attribs: &[VertexAttrib]) -> Self {
+        {
+            let active = self.bind();
+            let _buffer = instances.bind();
+            for a in attribs {
+                assert!(
+                    a.divisor > 0,
+                    "instance attribute {} has no divisor",
+                    a.location
+                );
+                active.vertex_attrib_pointer(a);
+            }
+        }
+
+        self.instances = Some(instances);
+        self
+    }
+
+    pub fn instances(&self) -> Option<&Buffer> {
+        self.instances.as_ref()
+    }
+
+    pub fn index_type(&self) -> Option<IndexType> {
+        self.ibo.as_ref().map(|&(_, ty)| ty)
+    }
+
+    /// The number of elements a full draw covers: the index count when indexed, otherwise the
+    /// vertex count.
+    pub fn count(&self) -> usize {
+        match self.ibo {
+            Some((ref ibo, ty)) => ibo.size() / ty.size(),
+            None if self.stride > 0 => self.vbo.size() / self.stride,
+            None => 0,
+        }
+    }
+
+    /// A range covering every element.
+    pub fn full_range(&self) -> DrawRange {
+        DrawRange::new(0, self.count())
+    }
+
+    pub fn bind(&self) -> ActiveVAO<'_> {
         ActiveVAO::new(self)
     }
 }
@@ -66,30 +228,94 @@ impl<'a> ActiveVAO<'a> {
         ActiveVAO { vao }
     }
 
-    fn vertex_attrib_pointer(&self, location: GLuint, size: GLint, stride: GLsizei, start: usize) {
+    fn vertex_attrib_pointer(&self, attrib: &VertexAttrib) {
+        unsafe {
+            gl::EnableVertexAttribArray(attrib.location);
+            if attrib.integer {
+                assert!(
+                    !attrib.ty.is_float(),
+                    "integer attribute {} has float components",
+                    attrib.location
+                );
+                gl::VertexAttribIPointer(
+                    attrib.location,
+                    attrib.size,
+                    attrib.ty.into(),
+                    attrib.stride,
+                    attrib.start as *const GLvoid,
+                );
+            } else {
+                gl::VertexAttribPointer(
+                    attrib.location,
+                    attrib.size,
+                    attrib.ty.into(),
+                    attrib.normalized as GLboolean,
+                    attrib.stride,
+                    attrib.start as *const GLvoid,
+                );
+            }
+            if attrib.divisor != 0 {
+                gl::VertexAttribDivisor(attrib.location, attrib.divisor);
+            }
+        }
+    }
+
+    pub fn draw(&self, _: &ActiveProgram, prim: GLenum, range: DrawRange) {
+        let count = range.count as GLsizei;
         unsafe {
-            gl::EnableVertexAttribArray(location);
-            gl::VertexAttribPointer(
-                location,
-                size,
-                gl::FLOAT,
-                gl::FALSE as GLboolean,
-                stride,
-                start as *const GLvoid,
-            );
+            match self.vao.ibo {
+                Some((_, ty)) => {
+                    let offset = (range.first * ty.size()) as *const GLvoid;
+                    if range.base_vertex == 0 {
+                        gl::DrawElements(prim, count, ty.into(), offset)
+                    } else {
+                        gl::DrawElementsBaseVertex(
+                            prim,
+                            count,
+                            ty.into(),
+                            offset,
+                            range.base_vertex,
+                        )
+                    }
+                }
+                None => gl::DrawArrays(prim, range.first as GLint + range.base_vertex, count),
+            }
         }
     }
 
-    pub fn draw(&self, _: &ActiveProgram, prim: GLenum, start: GLint, len: GLsizei) {
+    /// Draws `range` `instances` times, advancing the instance attributes between copies.
+    pub fn draw_instanced(
+        &self,
+        _: &ActiveProgram,
+        prim: GLenum,
+        range: DrawRange,
+        instances: usize,
+    ) {
+        let count = range.count as GLsizei;
+        let instances = instances as GLsizei;
         unsafe {
             match self.vao.ibo {
-                Some(_) => gl::DrawElements(
+                Some((_, ty)) => {
+                    let offset = (range.first * ty.size()) as *const GLvoid;
+                    if range.base_vertex == 0 {
+                        gl::DrawElementsInstanced(prim, count, ty.into(), offset, instances)
+                    } else {
+                        gl::DrawElementsInstancedBaseVertex(
+                            prim,
+                            count,
+                            ty.into(),
+                            offset,
+                            instances,
+                            range.base_vertex,
+                        )
+                    }
+                }
+                None => gl::DrawArraysInstanced(
                     prim,
-                    len as GLsizei,
-                    gl::UNSIGNED_SHORT,
-                    start as *const i32 as *const _,
+                    range.first as GLint + range.base_vertex,
+                    count,
+                    instances,
                 ),
-                None => gl::DrawArrays(prim, start, len),
             }
         }
     }
@@ -100,3 +326,36 @@ impl<'a> Drop for ActiveVAO<'a> {
         unsafe { gl::BindVertexArray(0) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tightly_packed_stride_is_attribute_size() {
+        let position = VertexAttrib {
+            size: 3,
+            ..Default::default()
+        };
+        assert_eq!(position.effective_stride(), 12);
+
+        let color = VertexAttrib {
+            size: 4,
+            ty: AttribType::U8,
+            normalized: true,
+            ..Default::default()
+        };
+        assert_eq!(color.effective_stride(), 4);
+    }
+
+    #[test]
+    fn explicit_stride_is_kept() {
+        let attrib = VertexAttrib {
+            size: 2,
+            stride: 32,
+            start: 12,
+            ..Default::default()
+        };
+        assert_eq!(attrib.effective_stride(), 32);
+    }
+}
@@ -0,0 +1,124 @@
+use std::mem;
+
+use cgmath::{Vector2, Vector3, Vector4};
+use gfx::typed_buffer::Pod;
+use gfx::vertex_array::{AttribType, VertexAttrib};
+
+use gl::types::*;
+
+/// Implements `VertexFormat` for a `#[repr(C)]` struct that is already `Pod`, assigning each
+/// listed field an attribute location.
+#[macro_export]
+macro_rules! vertex_format {
+    ($ty:ident { $($field:ident => $location:expr),* $(,)* }) => {
+        impl $crate::gfx::vertex_format::VertexFormat for $ty {
+            fn attribs() -> Vec<$crate::gfx::vertex_array::VertexAttrib> {
+                vec![$(
+                    $crate::gfx::vertex_format::field_attrib(
+                        |v: &$ty| &v.$field,
+                        $location,
+                        ::std::mem::offset_of!($ty, $field),
+                    )
+                ),*]
+            }
+        }
+    }
+}
+
+/// A `#[repr(C)]` vertex struct that knows its own attribute layout.
+///
+/// Implement it with the `vertex_format!` macro, which reads the field offsets off the struct.
+pub trait VertexFormat: Pod {
+    fn attribs() -> Vec<VertexAttrib>;
+
+    fn stride() -> usize {
+        mem::size_of::<Self>()
+    }
+}
+
+/// A type that can be a field of a `VertexFormat` struct.
+pub trait VertexField: Pod {
+    const TYPE: AttribType;
+    /// The number of components.
+    const SIZE: GLint;
+    const NORMALIZED: bool = false;
+    /// Whether the field feeds an `int`/`uint` shader input.
+    const INTEGER: bool = false;
+}
+
+/// Wraps integer vertex data that the shader reads as floats in `[0, 1]` or `[-1, 1]`, such
+/// as RGBA8 colors.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(transparent)]
+pub struct Normalized<T>(pub T);
+
+unsafe impl<T: Pod> Pod for Normalized<T> {}
+
+/// Builds the attribute for a field, taking its type from `field` and its offset from the
+/// caller. Used by `vertex_format!`.
+pub fn field_attrib<V, F, A>(_field: A, location: GLuint, start: usize) -> VertexAttrib
+where
+    V: VertexFormat,
+    F: VertexField,
+    A: Fn(&V) -> &F,
+{
+    VertexAttrib {
+        location,
+        size: F::SIZE,
+        stride: V::stride() as GLsizei,
+        start,
+        ty: F::TYPE,
+        normalized: F::NORMALIZED,
+        integer: F::INTEGER,
+        ..Default::default()
+    }
+}
+
+macro_rules! vertex_field {
+    ($ty:ty, $attrib:ident, $size:expr, integer) => {
+        impl VertexField for $ty {
+            const TYPE: AttribType = AttribType::$attrib;
+            const SIZE: GLint = $size;
+            const INTEGER: bool = true;
+        }
+    };
+    ($ty:ty, $attrib:ident, $size:expr, normalized) => {
+        impl VertexField for Normalized<$ty> {
+            const TYPE: AttribType = AttribType::$attrib;
+            const SIZE: GLint = $size;
+            const NORMALIZED: bool = true;
+        }
+    };
+    ($ty:ty, $attrib:ident, $size:expr) => {
+        impl VertexField for $ty {
+            const TYPE: AttribType = AttribType::$attrib;
+            const SIZE: GLint = $size;
+        }
+    };
+}
+
+vertex_field!(f32, F32, 1);
+vertex_field!([f32; 2], F32, 2);
+vertex_field!([f32; 3], F32, 3);
+vertex_field!([f32; 4], F32, 4);
+vertex_field!(Vector2<f32>, F32, 2);
+vertex_field!(Vector3<f32>, F32, 3);
+vertex_field!(Vector4<f32>, F32, 4);
+
+vertex_field!(i32, I32, 1, integer);
+vertex_field!([i32; 2], I32, 2, integer);
+vertex_field!([i32; 3], I32, 3, integer);
+vertex_field!([i32; 4], I32, 4, integer);
+vertex_field!(u32, U32, 1, integer);
+vertex_field!([u32; 2], U32, 2, integer);
+vertex_field!([u32; 3], U32, 3, integer);
+vertex_field!([u32; 4], U32, 4, integer);
+vertex_field!([u8; 4], U8, 4, integer);
+vertex_field!([u16; 4], U16, 4, integer);
+
+vertex_field!([u8; 4], U8, 4, normalized);
+vertex_field!([i8; 4], I8, 4, normalized);
+vertex_field!([u16; 2], U16, 2, normalized);
+vertex_field!([u16; 4], U16, 4, normalized);
+vertex_field!([i16; 2], I16, 2, normalized);
+vertex_field!([i16; 4], I16, 4, normalized);
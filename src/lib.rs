@@ -0,0 +1,7 @@
+extern crate cgmath;
+extern crate gl;
+extern crate image;
+
+pub mod gfx;
+pub mod mesh;
+pub mod transform;
@@ -8,8 +8,3 @@ macro_rules! include_res {
         include_bytes!(resource_root!($e))
     }
 }
-macro_rules! include_res_str {
-    ($e:expr) => {
-        include_str!(resource_root!($e))
-    }
-}
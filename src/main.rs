@@ -1,5 +1,7 @@
 extern crate cgmath;
 extern crate gl;
+#[macro_use]
+extern crate gl_sandbox;
 extern crate glutin;
 extern crate image;
 
@@ -7,37 +9,40 @@ extern crate image;
 mod macros;
 
 mod camera;
-mod gfx;
 mod input;
-mod transform;
-mod mesh;
+mod skybox;
 mod vertex;
 
-use std::io::{Cursor, Read};
+use std::io::Cursor;
 use std::mem;
 
+use gl_sandbox::{gfx, mesh, transform};
+
 use camera::Camera;
 use input::{KeyState, MouseState};
-use gfx::buffer::{Buffer, BufferType};
-use gfx::shader::{Program, Shader, ShaderStage, UniformValue};
-use gfx::texture::{MagnifyFilter, MinifyFilter, Texture, TextureUnit, WrapFunction};
+use gfx::buffer::BufferType;
+use gfx::preprocessor::Preprocessor;
+use gfx::reload::ReloadableProgram;
+use gfx::shader::ShaderStage;
+use gfx::texture::{CubeLayout, MagnifyFilter, MinifyFilter, MipmapGeneration, Texture,
+                   TextureTarget, TextureUnit, WrapFunction};
+use gfx::typed_buffer::TypedBuffer;
 use gfx::vertex_array::{VertexArray, VertexAttrib};
+use gfx::vertex_format::VertexFormat;
 use transform::Transform;
 use mesh::{DrawMode, Mesh};
+use skybox::Skybox;
 use vertex::Vertex;
 
-use cgmath::{Deg, Matrix4, Vector3};
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector3, Vector4};
+use gl::types::*;
 use glutin::{ContextBuilder, CursorState, DeviceEvent, ElementState, Event, EventsLoop, GlContext,
              GlProfile, GlWindow, MouseButton, MouseCursor, MouseScrollDelta, VirtualKeyCode,
              WindowBuilder, WindowEvent};
-use image::{DecodingResult, ImageDecoder};
 use image::png::PNGDecoder;
 
-// Shader sources
-static VS_SRC: &'static str = include_res_str!("triangle.vs");
-static FS_SRC: &'static str = include_res_str!("triangle.fs");
-
-static TEST_PNG: &'static [u8] = include_res!("test.png");
+static TEST_PNG: &[u8] = include_res!("test.png");
+static SKY_PNG: &[u8] = include_res!("sky.png");
 
 fn update_perspective(w: u32, h: u32) -> Matrix4<f32> {
     cgmath::perspective(Deg(70.0), w as f32 / h as f32, 0.001, 1000.0)
@@ -62,6 +67,7 @@ fn main() {
         gl_window.make_current().unwrap();
         gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
         gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
         gl::FrontFace(gl::CW);
         gl::ClearColor(0.0, 0.0, 0.0, 1.0);
     }
@@ -95,62 +101,42 @@ fn main() {
                 },
             ];
 
-            let vbo = Buffer::new(BufferType::Vertex);
-            vbo.bind().buffer(&Vertex::into_bytes(&verticies));
-            vbo
+            let vbo = TypedBuffer::new(BufferType::Vertex);
+            vbo.bind().buffer(&verticies);
+            vbo.into_inner()
         };
 
-        let indicies = [0u16, 1, 2, 2, 1, 3];
-        let ibo = {
-            let ibo = Buffer::new(BufferType::Index);
-            unsafe {
-                ibo.bind().buffer(std::slice::from_raw_parts(
-                    indicies.as_ptr() as *const u8,
-                    mem::size_of_val(&indicies),
-                ))
-            }
-            ibo
-        };
-        let vao = VertexArray::new(
-            vbo,
-            Some(ibo),
-            &[
-                VertexAttrib {
-                    location: 0,
-                    size: 3,
-                    stride: vertex::consts::SIZE as i32,
-                    start: vertex::consts::POSITION_START,
-                },
-                VertexAttrib {
-                    location: 1,
-                    size: 4,
-                    stride: vertex::consts::SIZE as i32,
-                    start: vertex::consts::COLOR_START,
-                },
-                VertexAttrib {
-                    location: 2,
-                    size: 2,
-                    stride: vertex::consts::SIZE as i32,
-                    start: vertex::consts::TEXCOORD_START,
-                },
-                VertexAttrib {
-                    location: 3,
-                    size: 3,
-                    stride: vertex::consts::SIZE as i32,
-                    start: vertex::consts::NORMAL_START,
-                },
-            ],
-        );
+        let ibo = TypedBuffer::new(BufferType::Index);
+        ibo.bind().buffer(&[0u16, 1, 2, 2, 1, 3]);
+        let vao = VertexArray::indexed(vbo, ibo, &Vertex::attribs());
         let transform = Transform {
             position: Vector3::new(0.0, 0.0, -1.0),
             scale: Vector3::new(1.0, 1.0, 1.0),
             ..Default::default()
         };
+
+        // One model matrix per copy of the quad
+        let instances = TypedBuffer::new(BufferType::Vertex);
+        instances
+            .bind()
+            .buffer(&[Matrix4::from(&transform), Matrix4::identity()]);
+        let model_attribs: Vec<_> = (0..4)
+            .map(|column| VertexAttrib {
+                location: 4 + column as GLuint,
+                size: 4,
+                stride: mem::size_of::<Matrix4<f32>>() as GLsizei,
+                start: column * mem::size_of::<Vector4<f32>>(),
+                divisor: 1,
+                ..Default::default()
+            })
+            .collect();
+        let vao = vao.with_instances(instances.into_inner(), &model_attribs);
+
         Mesh {
             transform,
-            vao,
             draw_mode: DrawMode::Triangles,
-            range: 0..indicies.len(),
+            submeshes: vec![vao.full_range()],
+            vao,
         }
     };
 
@@ -164,23 +150,49 @@ fn main() {
     };
     let mut perspective = update_perspective(width, height);
 
-    let program = Program::from_shaders(&[
-        Shader::from_source(ShaderStage::Vertex, VS_SRC),
-        Shader::from_source(ShaderStage::Fragment, FS_SRC),
-    ]);
-    let u_mvp = program.get_uniform_location("mvp");
-    let u_tex = program.get_uniform_location("tex");
+    let mut program = ReloadableProgram::new(
+        Preprocessor::new(resource_root!("")),
+        &[
+            (ShaderStage::Vertex, "triangle.vs"),
+            (ShaderStage::Fragment, "triangle.fs"),
+        ],
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
 
     let mut texture_unit = TextureUnit::take(1).unwrap();
     texture_unit.replace_texture(Texture::new());
     let active_tex = texture_unit.bind_texture().unwrap();
-    active_tex.write(PNGDecoder::new(Cursor::new(TEST_PNG)));
-    active_tex.set_minify_filter(MinifyFilter::Linear);
+    active_tex
+        .write_mipmapped(
+            PNGDecoder::new(Cursor::new(TEST_PNG)),
+            MipmapGeneration::Gpu,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    active_tex.set_minify_filter(MinifyFilter::LinearMipmapLinear);
+    active_tex.set_anisotropy(16.0);
     active_tex.set_magnify_filter(MagnifyFilter::Linear);
     active_tex.set_wrap_function((WrapFunction::Repeat, WrapFunction::Repeat));
 
-    let active_program = program.bind();
-    active_program.uniform(u_tex, UniformValue::I1(texture_unit.id()));
+    let mut sky_unit = TextureUnit::take_any().unwrap();
+    sky_unit.replace_texture(Texture::with_target(TextureTarget::CubeMap));
+    {
+        let active_sky = sky_unit.bind_texture().unwrap();
+        active_sky
+            .write_cube_layout(
+                PNGDecoder::new(Cursor::new(SKY_PNG)),
+                CubeLayout::HorizontalCross,
+            )
+            .unwrap_or_else(|e| panic!("{}", e));
+        active_sky.set_minify_filter(MinifyFilter::Linear);
+        active_sky.set_magnify_filter(MagnifyFilter::Linear);
+        active_sky.set_wrap_function((
+            WrapFunction::Clamp,
+            WrapFunction::Clamp,
+            WrapFunction::Clamp,
+        ));
+    }
+    let mut skybox = Skybox::new(Preprocessor::new(resource_root!("")), sky_unit)
+        .unwrap_or_else(|e| panic!("{}", e));
 
     let mut running = true;
     while running {
@@ -218,10 +230,8 @@ fn main() {
                 _ => (),
             },
             Event::DeviceEvent { event, .. } => match event {
-                DeviceEvent::MouseMotion { delta: (x, y) } => {
-                    if grabbed {
-                        mouse_state.position = (-x as i32, -y as i32);
-                    }
+                DeviceEvent::MouseMotion { delta: (x, y) } if grabbed => {
+                    mouse_state.position = (-x as i32, -y as i32);
                 }
                 DeviceEvent::MouseWheel {
                     delta: MouseScrollDelta::LineDelta(_, y),
@@ -237,7 +247,7 @@ fn main() {
             grabbed = true;
             window.set_cursor(MouseCursor::NoneCursor);
             // TODO: Fix this when https://github.com/tomaka/winit/pull/385 is merged
-            window.set_cursor_state(CursorState::Grab);
+            let _ = window.set_cursor_state(CursorState::Grab);
             window.set_cursor_state(CursorState::Normal).unwrap();
             window.set_cursor_state(CursorState::Grab).unwrap();
         }
@@ -265,18 +275,24 @@ fn main() {
             camera.position -= camera::UP * 0.1;
         }
 
+        if let Err(e) = program.reload_if_changed() {
+            eprintln!("{}", e);
+        }
+        if let Err(e) = skybox.reload_if_changed() {
+            eprintln!("{}", e);
+        }
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
-        let transform: Matrix4<f32> = (&mesh.transform).into();
-        let mvp = perspective * camera.get_view_matrix() * transform;
-        active_program.uniform(u_mvp, UniformValue::Matrix4(mvp));
-        mesh.draw(&active_program);
+        skybox.draw(perspective, &camera);
+
+        let active_program = program.bind();
+        active_program.set("tex", texture_unit.id());
 
-        let mvp = perspective * camera.get_view_matrix();
-        active_program.uniform(u_mvp, UniformValue::Matrix4(mvp));
-        mesh.draw(&active_program);
+        let view_projection = perspective * camera.get_view_matrix();
+        active_program.set("view_projection", view_projection);
+        mesh.draw_instanced(&active_program, 2);
 
         gl_window.swap_buffers().unwrap();
     }
@@ -1,7 +1,5 @@
-use std::ops::Range;
-
 use gfx::shader::ActiveProgram;
-use gfx::vertex_array::VertexArray;
+use gfx::vertex_array::{DrawRange, VertexArray};
 use transform::Transform;
 
 use gl;
@@ -12,29 +10,44 @@ pub struct Mesh {
     pub transform: Transform,
     pub vao: VertexArray,
     pub draw_mode: DrawMode,
-    pub range: Range<usize>,
+    /// The parts of `vao` making up the mesh, each drawn with its own call.
+    pub submeshes: Vec<DrawRange>,
 }
 
 impl Mesh {
     pub fn draw(&self, program: &ActiveProgram) {
-        self.vao.bind().draw(
-            program,
-            self.draw_mode.into(),
-            self.range.start as i32,
-            self.range.end as i32,
-        )
+        let active = self.vao.bind();
+        for &range in &self.submeshes {
+            active.draw(program, self.draw_mode.into(), range);
+        }
+    }
+
+    /// Draws every submesh `instances` times, using the vertex array's instance buffer.
+    pub fn draw_instanced(&self, program: &ActiveProgram, instances: usize) {
+        let active = self.vao.bind();
+        for &range in &self.submeshes {
+            active.draw_instanced(program, self.draw_mode.into(), range, instances);
+        }
+    }
+
+    pub fn draw_submesh(&self, program: &ActiveProgram, index: usize) {
+        self.vao
+            .bind()
+            .draw(program, self.draw_mode.into(), self.submeshes[index]);
     }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub enum DrawMode {
     Triangles,
+    Patches,
 }
 
-impl Into<GLenum> for DrawMode {
-    fn into(self) -> GLenum {
-        match self {
+impl From<DrawMode> for GLenum {
+    fn from(mode: DrawMode) -> Self {
+        match mode {
             DrawMode::Triangles => gl::TRIANGLES,
+            DrawMode::Patches => gl::PATCHES,
         }
     }
 }
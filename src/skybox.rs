@@ -0,0 +1,109 @@
+use std::mem;
+
+use camera::Camera;
+use gfx::buffer::BufferType;
+use gfx::preprocessor::Preprocessor;
+use gfx::reload::ReloadableProgram;
+use gfx::shader::{ShaderError, ShaderStage};
+use gfx::texture::TextureUnit;
+use gfx::typed_buffer::TypedBuffer;
+use gfx::vertex_array::{VertexArray, VertexAttrib};
+
+use cgmath::Matrix4;
+use gl;
+use gl::types::GLenum;
+
+/// Draws a cube map around the camera, behind everything else in the scene.
+pub struct Skybox {
+    program: ReloadableProgram,
+    vao: VertexArray,
+    texture_unit: TextureUnit,
+}
+
+impl Skybox {
+    /// Uses the cube map bound to `texture_unit`, and `skybox.vs`/`skybox.fs` relative to the
+    /// preprocessor root.
+    pub fn new(preprocessor: Preprocessor, texture_unit: TextureUnit) -> Result<Self, ShaderError> {
+        let program = ReloadableProgram::new(
+            preprocessor,
+            &[
+                (ShaderStage::Vertex, "skybox.vs"),
+                (ShaderStage::Fragment, "skybox.fs"),
+            ],
+        )?;
+
+        let corners: [[f32; 3]; 8] = [
+            [-1.0, -1.0, -1.0],
+            [1.0, -1.0, -1.0],
+            [-1.0, 1.0, -1.0],
+            [1.0, 1.0, -1.0],
+            [-1.0, -1.0, 1.0],
+            [1.0, -1.0, 1.0],
+            [-1.0, 1.0, 1.0],
+            [1.0, 1.0, 1.0],
+        ];
+        #[rustfmt::skip]
+        let indices = [
+            0, 1, 2, 2, 1, 3, // -Z
+            4, 6, 5, 5, 6, 7, // +Z
+            0, 2, 4, 4, 2, 6, // -X
+            1, 5, 3, 3, 5, 7, // +X
+            0, 4, 1, 1, 4, 5, // -Y
+            2, 3, 6, 6, 3, 7, // +Y
+        ];
+        let vbo = TypedBuffer::new(BufferType::Vertex);
+        vbo.bind().buffer(&corners);
+        let vao = VertexArray::with_indices(
+            vbo.into_inner(),
+            &indices,
+            &[VertexAttrib {
+                location: 0,
+                size: 3,
+                stride: mem::size_of::<[f32; 3]>() as i32,
+                ..Default::default()
+            }],
+        );
+
+        Ok(Skybox {
+            program,
+            vao,
+            texture_unit,
+        })
+    }
+
+    pub fn reload_if_changed(&mut self) -> Result<bool, ShaderError> {
+        self.program.reload_if_changed()
+    }
+
+    /// Draws the sky. Call before the rest of the scene, as it does not write depth.
+    pub fn draw(&self, perspective: Matrix4<f32>, camera: &Camera) {
+        let _texture = self.texture_unit.bind_texture();
+        let active_program = self.program.bind();
+        active_program.set("sky", self.texture_unit.id());
+        active_program.set(
+            "view_projection",
+            perspective * camera.get_view_rotation_matrix(),
+        );
+
+        // The cube is seen from inside, so don't cull it. Restore the caller's state afterwards
+        let cull = unsafe { gl::IsEnabled(gl::CULL_FACE) == gl::TRUE };
+        let (mut depth_func, mut depth_mask) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_func);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_mask);
+            gl::DepthMask(gl::FALSE);
+            gl::DepthFunc(gl::LEQUAL);
+            gl::Disable(gl::CULL_FACE);
+        }
+        self.vao
+            .bind()
+            .draw(&active_program, gl::TRIANGLES, self.vao.full_range());
+        unsafe {
+            if cull {
+                gl::Enable(gl::CULL_FACE);
+            }
+            gl::DepthFunc(depth_func as GLenum);
+            gl::DepthMask(depth_mask);
+        }
+    }
+}
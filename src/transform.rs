@@ -17,18 +17,20 @@ impl Default for Transform {
     }
 }
 
-impl Into<Matrix4<f32>> for Transform {
-    fn into(self) -> Matrix4<f32> {
-        Matrix4::from_translation(self.position)
-            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
-            * Matrix4::from(self.rotation)
+impl From<Transform> for Matrix4<f32> {
+    fn from(transform: Transform) -> Self {
+        Matrix4::from(&transform)
     }
 }
 
-impl<'a> Into<Matrix4<f32>> for &'a Transform {
-    fn into(self) -> Matrix4<f32> {
-        Matrix4::from_translation(self.position)
-            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
-            * Matrix4::from(self.rotation)
+impl<'a> From<&'a Transform> for Matrix4<f32> {
+    fn from(transform: &'a Transform) -> Self {
+        Matrix4::from_translation(transform.position)
+            * Matrix4::from_nonuniform_scale(
+                transform.scale.x,
+                transform.scale.y,
+                transform.scale.z,
+            )
+            * Matrix4::from(transform.rotation)
     }
 }
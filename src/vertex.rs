@@ -1,14 +1,8 @@
 use cgmath::{Vector2, Vector3, Vector4};
+use gfx::typed_buffer::Pod;
 
-pub mod consts {
-    pub const SIZE_F32: usize = 12;
-    pub const SIZE: usize = SIZE_F32 * 4;
-    pub const POSITION_START: usize = 0;
-    pub const COLOR_START: usize = 3 * 4;
-    pub const TEXCOORD_START: usize = 7 * 4;
-    pub const NORMAL_START: usize = 9 * 4;
-}
-
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
 pub struct Vertex {
     pub position: Vector3<f32>,
     pub color: Vector4<f32>,
@@ -16,6 +10,16 @@ pub struct Vertex {
     pub normal: Vector3<f32>,
 }
 
+// Every field is made of f32s, so there is no padding
+unsafe impl Pod for Vertex {}
+
+vertex_format!(Vertex {
+    position => 0,
+    color => 1,
+    tex_coord => 2,
+    normal => 3,
+});
+
 impl Default for Vertex {
     fn default() -> Self {
         Vertex {
@@ -26,35 +30,3 @@ impl Default for Vertex {
         }
     }
 }
-
-impl Vertex {
-    pub fn into_bytes(verticies: &[Vertex]) -> Vec<u8> {
-        let mut vec = Vec::with_capacity(consts::SIZE * verticies.len());
-        for v in verticies.iter() {
-            let t: [u8; consts::SIZE] = v.into();
-            vec.extend(t.iter());
-        }
-        vec
-    }
-}
-
-impl<'a> Into<[u8; consts::SIZE]> for &'a Vertex {
-    fn into(self) -> [u8; consts::SIZE] {
-        unsafe {
-            ::std::mem::transmute([
-                self.position.x,
-                self.position.y,
-                self.position.z,
-                self.color.x,
-                self.color.y,
-                self.color.z,
-                self.color.w,
-                self.tex_coord.x,
-                self.tex_coord.y,
-                self.normal.x,
-                self.normal.y,
-                self.normal.z,
-            ])
-        }
-    }
-}